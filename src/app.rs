@@ -0,0 +1,413 @@
+//! The command-line program: argument parsing, the default reduce run and the
+//! subcommands.
+
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::budget::{self, BudgetReport, Budgeted};
+use crate::cache::Cache;
+use crate::chunk;
+use crate::cli::{Cli, Command, Format, NsMode, TruncateBy};
+use crate::commands;
+use crate::config;
+use crate::filter::{EntryFilter, PathPattern};
+use crate::mirror;
+use crate::render::{
+    Doc, Trailer, render_compact, render_csv, render_json, render_markdown, render_ndjson,
+    render_toon, render_yaml,
+};
+use crate::util::{FileError, TokenBreakdown, count_tokens, walk_inputs};
+use crate::watch;
+use crate::xml::{FlatEntry, FlattenOptions, flatten_xml_file};
+
+pub fn run() -> Result<()> {
+    let cli = config::parse()?;
+
+    match &cli.command {
+        Some(Command::ToXml(args)) => commands::to_xml::run(args),
+        Some(Command::Patch(args)) => commands::patch::run(args),
+        Some(Command::Query(args)) => commands::query::run(args),
+        Some(Command::ExportSqlite(args)) => commands::export_sqlite::run(args),
+        None => reduce(&cli),
+    }
+}
+
+fn reduce(cli: &Cli) -> Result<()> {
+    let input = cli
+        .input
+        .as_deref()
+        .expect("clap requires --input when no subcommand is given");
+
+    let opts = flatten_options(cli)?;
+    let filter = EntryFilter::new(&cli.select, &cli.drop, &cli.where_)?;
+    let keep_string = keep_string_patterns(cli)?;
+    let typed = cli.typed.then_some(keep_string.as_slice());
+    let mut cache = open_cache(cli, &opts, &filter, typed);
+
+    if cli.watch {
+        return watch::run(cli, input, &opts, &filter, typed, cache);
+    }
+
+    let inputs = walk_inputs(input, cli.recursive, &cli.glob)
+        .with_context(|| format!("Failed to enumerate input path(s): {}", input.display()))?;
+
+    if inputs.is_empty() {
+        anyhow::bail!(
+            "No input files matched (input={}, glob={})",
+            input.display(),
+            cli.glob
+        );
+    }
+
+    // Without a budget, chunks or a mirror nothing needs the whole set, so write
+    // lines as they come.
+    if cli.format == Format::Ndjson
+        && cli.max_tokens.is_none()
+        && cli.chunk_tokens.is_none()
+        && cli.output_dir.is_none()
+    {
+        return stream_ndjson(cli, inputs, &opts, &filter, typed, cache.as_mut());
+    }
+
+    let mut docs: Vec<Doc> = Vec::with_capacity(inputs.len());
+    let mut errors: Vec<FileError> = Vec::new();
+
+    for path in inputs {
+        match process_one(&path, cli, &opts, &filter, typed, cache.as_mut()) {
+            Ok(doc) => docs.push(doc),
+            Err(err) if cli.keep_going => errors.push(FileError::from_error(path, &err)),
+            Err(err) => return Err(err),
+        }
+    }
+    if let Some(cache) = &cache {
+        eprintln!("{}", cache.summary());
+    }
+
+    let assembler = Assembler {
+        cli,
+        never_truncate: &opts.never_truncate,
+        typed,
+        errors: cli.keep_going.then_some(errors.as_slice()),
+        always_wrap: cli.always_wrap,
+    };
+
+    if let (Some(limit), Some(dir)) = (cli.chunk_tokens, &cli.output_dir) {
+        // Chunks are always wrapped so each file names its sources; errors go to the manifest.
+        let chunker = Assembler {
+            errors: None,
+            always_wrap: true,
+            ..assembler
+        };
+        let manifest = chunk::write_chunks(
+            docs,
+            &errors,
+            limit,
+            dir,
+            cli.format.extension(),
+            cli.tokenizer.name(),
+            &chunker,
+        )?;
+        eprintln!(
+            "Wrote {} chunk(s) of at most {limit} tokens to {}",
+            manifest.chunks.len(),
+            dir.display()
+        );
+        return finish(cli, &errors);
+    }
+
+    let budget = match cli.max_tokens {
+        Some(limit) => budget::fit(&mut docs, limit, &assembler)?,
+        None => None,
+    };
+    if let Some(report) = &budget {
+        eprintln!(
+            "Output reduced from {} to {} tokens (limit {})",
+            report.original_tokens, report.final_tokens, report.limit
+        );
+    }
+
+    let mut tokens = TokenBreakdown::default();
+    if cli.show_tokens || cli.show_tokens_per_file {
+        for doc in &docs {
+            let per_file = TokenBreakdown::new(&doc.body, &doc.entries, cli.tokenizer);
+            if cli.show_tokens_per_file {
+                eprintln!(
+                    "{} -> {}",
+                    doc.path.display(),
+                    per_file.summary(cli.tokenizer)
+                );
+            }
+            tokens.add(&per_file);
+        }
+    }
+
+    if let Some(dir) = &cli.output_dir {
+        // One file per input, so nothing is wrapped; skipped files are only reported on stderr.
+        for doc in &docs {
+            let out = mirror::mirror_path(input, &doc.path, dir, cli.format.extension());
+            mirror::write(&out, &doc.body)?;
+        }
+        eprintln!("Wrote {} file(s) to {}", docs.len(), dir.display());
+        if cli.show_tokens {
+            tokens.total = docs
+                .iter()
+                .map(|d| count_tokens(&d.body, cli.tokenizer))
+                .sum();
+            eprintln!("Output: {}", tokens.summary(cli.tokenizer));
+        }
+        return finish(cli, &errors);
+    }
+
+    let final_text = assembler.assemble(&docs, budget.as_ref())?;
+
+    if cli.show_tokens {
+        tokens.total = count_tokens(&final_text, cli.tokenizer);
+        eprintln!("Output: {}", tokens.summary(cli.tokenizer));
+    }
+
+    match &cli.output {
+        Some(out_path) => {
+            std::fs::write(out_path, final_text)
+                .with_context(|| format!("Failed to write output file: {}", out_path.display()))?;
+        }
+        None => {
+            print!("{final_text}");
+        }
+    }
+
+    finish(cli, &errors)
+}
+
+/// `--format ndjson`: write each file's lines as soon as it is flattened.
+fn stream_ndjson(
+    cli: &Cli,
+    inputs: Vec<PathBuf>,
+    opts: &FlattenOptions,
+    filter: &EntryFilter,
+    typed: Option<&[PathPattern]>,
+    mut cache: Option<&mut Cache>,
+) -> Result<()> {
+    let mut out: Box<dyn Write> = match &cli.output {
+        Some(out_path) => Box::new(BufWriter::new(
+            File::create(out_path)
+                .with_context(|| format!("Failed to write output file: {}", out_path.display()))?,
+        )),
+        None => Box::new(BufWriter::new(std::io::stdout().lock())),
+    };
+
+    let mut errors: Vec<FileError> = Vec::new();
+    let mut tokens = TokenBreakdown::default();
+    for path in inputs {
+        let doc = match process_one(&path, cli, opts, filter, typed, cache.as_deref_mut()) {
+            Ok(doc) => doc,
+            Err(err) if cli.keep_going => {
+                errors.push(FileError::from_error(path, &err));
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+        if cli.show_tokens || cli.show_tokens_per_file {
+            let per_file = TokenBreakdown::new(&doc.body, &doc.entries, cli.tokenizer);
+            if cli.show_tokens_per_file {
+                eprintln!(
+                    "{} -> {}",
+                    doc.path.display(),
+                    per_file.summary(cli.tokenizer)
+                );
+            }
+            tokens.add(&per_file);
+            tokens.total += per_file.total;
+        }
+        out.write_all(doc.body.as_bytes())?;
+    }
+
+    let trailer = Trailer {
+        errors: Some(&errors),
+        budget: None,
+    };
+    out.write_all(render_ndjson::trailer_lines(trailer)?.as_bytes())?;
+    out.flush()?;
+
+    if let Some(cache) = &cache {
+        eprintln!("{}", cache.summary());
+    }
+    if cli.show_tokens {
+        eprintln!("Output: {}", tokens.summary(cli.tokenizer));
+    }
+    finish(cli, &errors)
+}
+
+/// Report skipped files and decide the exit status.
+fn finish(cli: &Cli, errors: &[FileError]) -> Result<()> {
+    for e in errors {
+        eprintln!("skipped {e}");
+    }
+
+    if cli.fail_on_error && !errors.is_empty() {
+        anyhow::bail!("{} input file(s) failed to parse", errors.len());
+    }
+
+    Ok(())
+}
+
+/// Renders documents and joins them into the final output text.
+#[derive(Clone, Copy)]
+struct Assembler<'a> {
+    cli: &'a Cli,
+    never_truncate: &'a [PathPattern],
+    /// `--typed`, with the `--keep-string` patterns.
+    typed: Option<&'a [PathPattern]>,
+    /// Listed in the trailer (with --keep-going).
+    errors: Option<&'a [FileError]>,
+    always_wrap: bool,
+}
+
+impl Budgeted for Assembler<'_> {
+    fn render(&self, path: &Path, entries: &[FlatEntry]) -> Result<String> {
+        render_entries(path, entries, self.cli, self.typed)
+    }
+
+    fn assemble(&self, docs: &[Doc], budget: Option<&BudgetReport>) -> Result<String> {
+        let cli = self.cli;
+        let trailer = Trailer {
+            errors: self.errors,
+            budget,
+        };
+
+        if docs.len() == 1 && trailer.is_empty() && !self.always_wrap {
+            return Ok(docs[0].body.clone());
+        }
+        // Multi-doc wrapper (keeps format valid/usable).
+        Ok(match cli.format {
+            Format::Json => {
+                render_json::wrap_many(docs, trailer, cli.json_mode, cli.minify, self.typed)?
+            }
+            Format::Toon => render_toon::wrap_many(docs, trailer),
+            Format::Compact => render_compact::wrap_many(docs, trailer),
+            Format::Yaml => render_yaml::wrap_many(docs, trailer, cli.yaml_mode)?,
+            Format::Ndjson => render_ndjson::wrap_many(docs, trailer)?,
+            Format::Markdown => render_markdown::wrap_many(docs, trailer),
+            Format::Csv => render_csv::wrap_many(docs),
+        })
+    }
+
+    fn count(&self, text: &str) -> usize {
+        count_tokens(text, self.cli.tokenizer)
+    }
+
+    fn keep_whole(&self, path: &str) -> bool {
+        self.never_truncate.iter().any(|p| p.matches(path))
+    }
+}
+
+fn render_entries(
+    path: &Path,
+    entries: &[FlatEntry],
+    cli: &Cli,
+    typed: Option<&[PathPattern]>,
+) -> Result<String> {
+    Ok(match cli.format {
+        Format::Json => render_json::render(entries, cli.json_mode, cli.minify, typed)?,
+        Format::Toon => render_toon::render(entries),
+        Format::Compact => render_compact::render(entries),
+        Format::Yaml => render_yaml::render(entries, cli.yaml_mode)?,
+        Format::Ndjson => render_ndjson::render(path, entries, cli.ndjson_mode, typed)?,
+        Format::Markdown => render_markdown::render(path, entries)?,
+        Format::Csv => render_csv::render(path, entries, cli.csv_table)?,
+    })
+}
+
+fn flatten_options(cli: &Cli) -> Result<FlattenOptions> {
+    Ok(FlattenOptions {
+        include_attributes: cli.include_attributes,
+        include_text: true,
+        include_cdata: true,
+        ns_mode: if cli.keep_ns_prefix {
+            NsMode::Prefix
+        } else {
+            cli.ns_mode
+        },
+        max_text_len: cli.max_text_len,
+        truncate_tokenizer: (cli.truncate_by == TruncateBy::Tokens).then_some(cli.tokenizer),
+        never_truncate: cli
+            .never_truncate
+            .iter()
+            .filter(|p| !p.is_empty())
+            .map(|p| PathPattern::element_or_pattern(p))
+            .collect::<Result<_>>()?,
+        path_style: cli.path_style,
+        with_locations: cli.with_locations,
+        include_comments: cli.include_comments,
+        preserve_space: cli.preserve_space.clone(),
+    })
+}
+
+/// Element names or patterns under which `--typed` leaves values as strings.
+fn keep_string_patterns(cli: &Cli) -> Result<Vec<PathPattern>> {
+    cli.keep_string
+        .iter()
+        .filter(|p| !p.is_empty())
+        .map(|p| PathPattern::element_or_pattern(p))
+        .collect()
+}
+
+/// The cache for this run, unless `--no-cache` is given. A cache directory that
+/// cannot be created only costs the speed-up.
+fn open_cache(
+    cli: &Cli,
+    opts: &FlattenOptions,
+    filter: &EntryFilter,
+    typed: Option<&[PathPattern]>,
+) -> Option<Cache> {
+    if cli.no_cache {
+        return None;
+    }
+    let settings = format!(
+        "{opts:?} {filter:?} {typed:?} {:?} {:?} {} {:?} {:?} {:?}",
+        cli.format, cli.json_mode, cli.minify, cli.yaml_mode, cli.ndjson_mode, cli.csv_table
+    );
+    Cache::open(&cli.cache_dir, settings)
+        .inspect_err(|err| eprintln!("warning: cache disabled: {err:#}"))
+        .ok()
+}
+
+pub(crate) fn process_one(
+    path: &Path,
+    cli: &Cli,
+    opts: &FlattenOptions,
+    filter: &EntryFilter,
+    typed: Option<&[PathPattern]>,
+    cache: Option<&mut Cache>,
+) -> Result<Doc> {
+    if !path.exists() {
+        anyhow::bail!("Input file does not exist: {}", path.display());
+    }
+    match cache {
+        Some(cache) => cache.get_or_insert(path, || flatten_one(path, cli, opts, filter, typed)),
+        None => flatten_one(path, cli, opts, filter, typed),
+    }
+}
+
+fn flatten_one(
+    path: &Path,
+    cli: &Cli,
+    opts: &FlattenOptions,
+    filter: &EntryFilter,
+    typed: Option<&[PathPattern]>,
+) -> Result<Doc> {
+    // Flatten any XML into generic (path, kind, value) entries.
+    // If parsing fails, you can decide to hard-fail or skip; we hard-fail by default.
+    let mut flat = flatten_xml_file(path, opts)
+        .with_context(|| format!("XML flatten failed: {}", path.display()))?;
+    filter.apply(&mut flat);
+
+    let rendered = render_entries(path, &flat, cli, typed)?;
+
+    Ok(Doc {
+        path: path.to_path_buf(),
+        entries: flat,
+        body: rendered,
+    })
+}
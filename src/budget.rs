@@ -0,0 +1,266 @@
+//! Fit rendered output under a hard token limit (`--max-tokens`).
+//!
+//! Reductions are applied from least to most destructive, re-measuring after each:
+//! shorter text values, no descriptions, collapsed low-value sections, and finally
+//! whole files dropped from the end. Every step is recorded so the output can say
+//! what is missing.
+
+use crate::render::Doc;
+use crate::util::truncate_chars;
+use crate::xml::tree::path_segments;
+use crate::xml::{FlatEntry, ValueKind};
+use anyhow::{Result, bail};
+use serde::Serialize;
+use std::path::Path;
+
+/// Successive caps for text values, in chars.
+const TEXT_LIMITS: [usize; 6] = [1024, 512, 256, 128, 64, 32];
+
+/// Elements that carry prose rather than configuration.
+const DESCRIPTION_ELEMENTS: [&str; 4] = ["description", "inlineHelpText", "helpText", "comments"];
+
+/// Top-level sections that are bulky and rarely matter for analysis, in the order
+/// they are collapsed. Permission, access and login sections never are: a profile
+/// review must not lose them silently.
+const LOW_VALUE_SECTIONS: [&str; 5] = [
+    "listViews",
+    "searchLayouts",
+    "compactLayouts",
+    "webLinks",
+    "layoutAssignments",
+];
+
+/// What `fit` removed to get under the limit.
+#[derive(Debug, Clone, Serialize)]
+pub struct BudgetReport {
+    pub limit: usize,
+    pub original_tokens: usize,
+    pub final_tokens: usize,
+    pub reductions: Vec<String>,
+}
+
+/// Hooks into the pipeline: how to render one document's entries (NDJSON names the
+/// file on every line), how to join documents (and the report trailer) into the
+/// output, and how to count tokens.
+pub trait Budgeted {
+    fn render(&self, path: &Path, entries: &[FlatEntry]) -> Result<String>;
+    fn assemble(&self, docs: &[Doc], report: Option<&BudgetReport>) -> Result<String>;
+    fn count(&self, text: &str) -> usize;
+
+    /// Values under this path must not be shortened (`--never-truncate`).
+    fn keep_whole(&self, _path: &str) -> bool {
+        false
+    }
+
+    fn measure(&self, docs: &[Doc], report: Option<&BudgetReport>) -> Result<usize> {
+        Ok(self.count(&self.assemble(docs, report)?))
+    }
+}
+
+/// Reduce `docs` until the assembled output fits `limit` tokens. Returns `None`
+/// when the output already fits, and fails when it is still too large after every
+/// reduction (the wrapper and trailer alone, or a single entry, can exceed it).
+pub fn fit(docs: &mut Vec<Doc>, limit: usize, ctx: &impl Budgeted) -> Result<Option<BudgetReport>> {
+    let original = ctx.measure(docs, None)?;
+    if original <= limit {
+        return Ok(None);
+    }
+
+    let mut report = BudgetReport {
+        limit,
+        original_tokens: original,
+        final_tokens: original,
+        reductions: Vec::new(),
+    };
+
+    let fits = |docs: &[Doc], report: &mut BudgetReport| -> Result<bool> {
+        report.final_tokens = ctx.measure(docs, Some(report))?;
+        Ok(report.final_tokens <= limit)
+    };
+
+    for max in TEXT_LIMITS {
+        let n = map_entries(docs, ctx, |entries| shorten_values(entries, max, ctx))?;
+        if n > 0 {
+            // Only the tightest cap matters to the reader.
+            report
+                .reductions
+                .retain(|r| !r.starts_with("text values cut"));
+            report
+                .reductions
+                .push(format!("text values cut to {max} chars ({n} entries)"));
+            if fits(docs, &mut report)? {
+                return Ok(Some(report));
+            }
+        }
+    }
+
+    let n = map_entries(docs, ctx, drop_descriptions)?;
+    if n > 0 {
+        report
+            .reductions
+            .push(format!("dropped descriptions and help text ({n} entries)"));
+        if fits(docs, &mut report)? {
+            return Ok(Some(report));
+        }
+    }
+
+    for section in LOW_VALUE_SECTIONS {
+        let n = map_entries(docs, ctx, |entries| collapse_section(entries, section))?;
+        if n > 0 {
+            report
+                .reductions
+                .push(format!("collapsed `{section}` sections ({n} entries)"));
+            if fits(docs, &mut report)? {
+                return Ok(Some(report));
+            }
+        }
+    }
+
+    // Drop whole files from the end: estimate from per-file counts, then confirm.
+    let costs: Vec<usize> = docs.iter().map(|d| ctx.count(&d.body)).collect();
+    let overshoot = report.final_tokens - limit;
+    let mut freed = 0;
+    let mut keep = docs.len();
+    while keep > 1 && freed < overshoot {
+        keep -= 1;
+        freed += costs[keep];
+    }
+    let mut omitted: Vec<String> = Vec::new();
+    loop {
+        for d in docs.drain(keep..) {
+            omitted.push(d.path.display().to_string());
+        }
+        if !omitted.is_empty() {
+            report.reductions.retain(|r| !r.starts_with("omitted "));
+            report.reductions.push(format!(
+                "omitted {} file(s): {}",
+                omitted.len(),
+                omitted.join(", ")
+            ));
+        }
+        if fits(docs, &mut report)? || docs.len() <= 1 {
+            break;
+        }
+        keep = docs.len() - 1;
+    }
+
+    // A single file that is still too large loses its trailing entries.
+    if report.final_tokens > limit
+        && let Some(doc) = docs.last_mut()
+    {
+        let total = doc.entries.len();
+        let file = doc.path.display().to_string();
+        let note = |kept: usize| format!("cut {file} after {kept} of {total} entries");
+        let (mut lo, mut hi) = (0, total);
+        // Wrappers build their output from the entries, so keep them in step with the
+        // body, and measure with the note the trailer will carry.
+        let all = doc.entries.clone();
+        while lo < hi {
+            let mid = (lo + hi).div_ceil(2);
+            doc.entries.clear();
+            doc.entries.extend_from_slice(&all[..mid]);
+            doc.body = ctx.render(&doc.path, &doc.entries)?;
+            report.reductions.push(note(mid));
+            let size = ctx.measure(std::slice::from_ref(doc), Some(&report))?;
+            report.reductions.pop();
+            if size <= limit {
+                lo = mid;
+            } else {
+                hi = mid - 1;
+            }
+        }
+        doc.entries = all;
+        doc.entries.truncate(lo);
+        doc.body = ctx.render(&doc.path, &doc.entries)?;
+        report.reductions.push(note(lo));
+        fits(docs, &mut report)?;
+    }
+
+    if report.final_tokens > limit {
+        bail!(
+            "--max-tokens {limit} cannot be met: the output still has {} tokens after every \
+             reduction ({})",
+            report.final_tokens,
+            report.reductions.join("; ")
+        );
+    }
+    Ok(Some(report))
+}
+
+/// Apply `f` to every document's entries, re-rendering the ones it changed.
+/// Returns the number of entries affected.
+fn map_entries(
+    docs: &mut [Doc],
+    ctx: &impl Budgeted,
+    mut f: impl FnMut(&mut Vec<FlatEntry>) -> usize,
+) -> Result<usize> {
+    let mut total = 0;
+    for doc in docs.iter_mut() {
+        let n = f(&mut doc.entries);
+        if n > 0 {
+            doc.body = ctx.render(&doc.path, &doc.entries)?;
+            total += n;
+        }
+    }
+    Ok(total)
+}
+
+fn shorten_values(entries: &mut [FlatEntry], max: usize, ctx: &impl Budgeted) -> usize {
+    let mut n = 0;
+    for e in entries.iter_mut() {
+        if !ctx.keep_whole(&e.p) && truncate_chars(&mut e.v, max) {
+            n += 1;
+        }
+    }
+    n
+}
+
+fn drop_descriptions(entries: &mut Vec<FlatEntry>) -> usize {
+    let before = entries.len();
+    entries.retain(|e| {
+        !element_names(&e.p)
+            .last()
+            .is_some_and(|name| DESCRIPTION_ELEMENTS.contains(&name))
+    });
+    before - entries.len()
+}
+
+/// Replace every entry under `/Root[0]/<section>[*]` with one `#comment` entry on
+/// the root saying what was removed, which `to-xml` and `patch` read like any comment.
+fn collapse_section(entries: &mut Vec<FlatEntry>, section: &str) -> usize {
+    let in_section = |p: &str| element_names(p).nth(1) == Some(section);
+
+    let Some(first) = entries.iter().position(|e| in_section(&e.p)) else {
+        return 0;
+    };
+    let removed = entries.iter().filter(|e| in_section(&e.p)).count();
+    let elements = entries
+        .iter()
+        .filter(|e| in_section(&e.p))
+        .filter_map(|e| path_segments(&e.p).get(1).map(|s| s.to_string()))
+        .collect::<std::collections::HashSet<_>>()
+        .len();
+
+    let p = &entries[first].p;
+    let sep = if p.starts_with('/') { "/" } else { "." };
+    let root = path_segments(p).first().copied().unwrap_or_default();
+    let lead = if sep == "/" { "/" } else { "" };
+    let placeholder = FlatEntry {
+        p: format!("{lead}{root}{sep}#comment"),
+        k: ValueKind::Comment,
+        v: format!("{elements} {section} elements collapsed by the token budget"),
+        l: None,
+    };
+
+    entries.retain(|e| !in_section(&e.p));
+    entries.insert(first, placeholder);
+    removed
+}
+
+/// Element names along a path, without indexes or the `#text`/`@attr` leaf.
+fn element_names(p: &str) -> impl Iterator<Item = &str> {
+    path_segments(p)
+        .into_iter()
+        .filter(|s| !s.starts_with('#') && !s.starts_with('@'))
+        .map(|s| s.rsplit_once('[').map_or(s, |(name, _)| name))
+}
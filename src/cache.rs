@@ -0,0 +1,113 @@
+//! On-disk cache of flattened and rendered files (`.sfmeta-cache/`, `--cache-dir`).
+//!
+//! An entry is keyed by the blake3 hash of the file contents together with its
+//! path, every option that changes the output, and the tool version, so a stale
+//! entry is never read back: a change to any of them simply misses. Entries are
+//! never pruned; delete the directory to reclaim the space.
+
+use crate::render::Doc;
+use crate::xml::FlatEntry;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    entries: Vec<FlatEntry>,
+    body: String,
+}
+
+pub struct Cache {
+    dir: PathBuf,
+    /// Debug form of the flatten, filter and render options.
+    settings: String,
+    pub hits: usize,
+    pub misses: usize,
+}
+
+impl Cache {
+    pub fn open(dir: &Path, settings: String) -> Result<Self> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create cache directory: {}", dir.display()))?;
+        // Keep the cache out of version control without touching the project's ignore files.
+        let ignore = dir.join(".gitignore");
+        if !ignore.exists() {
+            std::fs::write(&ignore, "*\n")
+                .with_context(|| format!("Failed to write {}", ignore.display()))?;
+        }
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            settings,
+            hits: 0,
+            misses: 0,
+        })
+    }
+
+    /// The cached document for `path`, or `make`'s result, which is then stored.
+    /// Unreadable entries count as misses and failed writes are ignored: the
+    /// cache only ever saves work.
+    pub fn get_or_insert(
+        &mut self,
+        path: &Path,
+        make: impl FnOnce() -> Result<Doc>,
+    ) -> Result<Doc> {
+        let content = std::fs::read(path)
+            .with_context(|| format!("Failed to read input file: {}", path.display()))?;
+        let file = self.dir.join(format!("{}.json", self.key(path, &content)));
+
+        if let Some(entry) = std::fs::read(&file)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<Entry>(&bytes).ok())
+        {
+            self.hits += 1;
+            return Ok(Doc {
+                path: path.to_path_buf(),
+                entries: entry.entries,
+                body: entry.body,
+            });
+        }
+
+        self.misses += 1;
+        let doc = make()?;
+        let entry = Entry {
+            entries: doc.entries,
+            body: doc.body,
+        };
+        // Written under a temporary name first so a concurrent run never reads half a file.
+        if let Ok(bytes) = serde_json::to_vec(&entry) {
+            let tmp = file.with_extension(format!("{}.tmp", std::process::id()));
+            if std::fs::write(&tmp, bytes).is_ok() && std::fs::rename(&tmp, &file).is_err() {
+                let _ = std::fs::remove_file(&tmp);
+            }
+        }
+        Ok(Doc {
+            path: doc.path,
+            entries: entry.entries,
+            body: entry.body,
+        })
+    }
+
+    fn key(&self, path: &Path, content: &[u8]) -> String {
+        let mut h = blake3::Hasher::new();
+        for part in [
+            env!("CARGO_PKG_VERSION").as_bytes(),
+            self.settings.as_bytes(),
+            // Several formats name the file in their output.
+            path.to_string_lossy().as_bytes(),
+        ] {
+            h.update(&part.len().to_le_bytes());
+            h.update(part);
+        }
+        h.update(content);
+        h.finalize().to_hex().to_string()
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "Cache: {} hit(s), {} miss(es) in {}",
+            self.hits,
+            self.misses,
+            self.dir.display()
+        )
+    }
+}
@@ -0,0 +1,176 @@
+//! Split wrapped output into numbered files that each fit a context window
+//! (`--chunk-tokens N --output-dir DIR`).
+//!
+//! Documents are packed greedily in input order. A document only spans several
+//! chunks when it alone is larger than the limit; it is then cut between entries.
+
+use crate::budget::Budgeted;
+use crate::render::Doc;
+use crate::util::FileError;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Serialize)]
+pub struct Manifest {
+    pub chunk_tokens: usize,
+    pub tokenizer: &'static str,
+    pub chunks: Vec<ChunkInfo>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FileError>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChunkInfo {
+    pub file: String,
+    pub tokens: usize,
+    pub files: Vec<ChunkFile>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChunkFile {
+    pub path: PathBuf,
+    pub tokens: usize,
+    /// `"2/3"` when the file was too large for one chunk and had to be cut.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub part: Option<String>,
+}
+
+/// A document (or part of one) placed in a chunk, with its standalone token cost.
+struct Piece {
+    doc: Doc,
+    tokens: usize,
+    part: Option<String>,
+}
+
+/// The documents of one chunk, and what the manifest says about each.
+#[derive(Default)]
+struct Chunk {
+    docs: Vec<Doc>,
+    files: Vec<ChunkFile>,
+}
+
+/// Pack `docs` into chunks of at most `limit` tokens each. Every candidate chunk
+/// is measured as assembled, so wrapper headers and separators count too.
+fn plan(docs: Vec<Doc>, limit: usize, ctx: &impl Budgeted) -> Result<Vec<Chunk>> {
+    let mut chunks: Vec<Chunk> = Vec::new();
+    let mut current = Chunk::default();
+
+    for doc in docs {
+        let tokens = ctx.measure(std::slice::from_ref(&doc), None)?;
+        let pieces = if tokens > limit {
+            split_doc(doc, limit, ctx)?
+        } else {
+            vec![Piece {
+                doc,
+                tokens,
+                part: None,
+            }]
+        };
+
+        for piece in pieces {
+            current.docs.push(piece.doc);
+            if current.docs.len() > 1 && ctx.measure(&current.docs, None)? > limit {
+                let doc = current.docs.pop().expect("just pushed");
+                chunks.push(std::mem::take(&mut current));
+                current.docs.push(doc);
+            }
+            current.files.push(ChunkFile {
+                path: current.docs.last().expect("just pushed").path.clone(),
+                tokens: piece.tokens,
+                part: piece.part,
+            });
+        }
+    }
+    if !current.docs.is_empty() {
+        chunks.push(current);
+    }
+    Ok(chunks)
+}
+
+/// Cut an oversized document between entries into parts that each fit `limit`.
+fn split_doc(doc: Doc, limit: usize, ctx: &impl Budgeted) -> Result<Vec<Piece>> {
+    let mut parts: Vec<Piece> = Vec::new();
+    let mut rest = &doc.entries[..];
+
+    while !rest.is_empty() {
+        // Largest prefix that fits; always take at least one entry to make progress.
+        let (mut lo, mut hi) = (1, rest.len());
+        while lo < hi {
+            let mid = (lo + hi).div_ceil(2);
+            if measure_part(&doc, &rest[..mid], ctx)?.1 <= limit {
+                lo = mid;
+            } else {
+                hi = mid - 1;
+            }
+        }
+        let (part, tokens) = measure_part(&doc, &rest[..lo], ctx)?;
+        parts.push(Piece {
+            doc: part,
+            tokens,
+            part: None,
+        });
+        rest = &rest[lo..];
+    }
+
+    let n = parts.len();
+    for (i, p) in parts.iter_mut().enumerate() {
+        p.part = Some(format!("{}/{n}", i + 1));
+    }
+    Ok(parts)
+}
+
+fn measure_part(
+    doc: &Doc,
+    entries: &[crate::xml::FlatEntry],
+    ctx: &impl Budgeted,
+) -> Result<(Doc, usize)> {
+    let part = Doc {
+        path: doc.path.clone(),
+        entries: entries.to_vec(),
+        body: ctx.render(&doc.path, entries)?,
+    };
+    let tokens = ctx.measure(std::slice::from_ref(&part), None)?;
+    Ok((part, tokens))
+}
+
+/// Write `chunk-NNN.<ext>` files plus `manifest.json` into `dir`.
+pub fn write_chunks(
+    docs: Vec<Doc>,
+    errors: &[FileError],
+    limit: usize,
+    dir: &Path,
+    ext: &str,
+    tokenizer: &'static str,
+    ctx: &impl Budgeted,
+) -> Result<Manifest> {
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create output directory: {}", dir.display()))?;
+
+    let mut manifest = Manifest {
+        chunk_tokens: limit,
+        tokenizer,
+        chunks: Vec::new(),
+        errors: errors.to_vec(),
+    };
+
+    for (i, chunk) in plan(docs, limit, ctx)?.into_iter().enumerate() {
+        let name = format!("chunk-{:03}.{ext}", i + 1);
+        let text = ctx.assemble(&chunk.docs, None)?;
+        let out_path = dir.join(&name);
+        std::fs::write(&out_path, &text)
+            .with_context(|| format!("Failed to write chunk: {}", out_path.display()))?;
+
+        manifest.chunks.push(ChunkInfo {
+            file: name,
+            tokens: ctx.count(&text),
+            files: chunk.files,
+        });
+    }
+
+    let manifest_path = dir.join("manifest.json");
+    std::fs::write(&manifest_path, serde_json::to_string_pretty(&manifest)?)
+        .with_context(|| format!("Failed to write manifest: {}", manifest_path.display()))?;
+
+    Ok(manifest)
+}
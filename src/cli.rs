@@ -1,13 +1,21 @@
-use clap::{Parser, ValueEnum};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 
+pub use crate::render::{CsvTable, JsonMode, NdjsonMode, YamlMode};
+pub use crate::util::Tokenizer;
+pub use crate::xml::{NsMode, PathStyle};
+
 #[derive(Parser, Debug)]
 #[command(name = "sfmeta-reader")]
 #[command(about = "Generic XML reducer for Salesforce metadata (works with ALL 300+ types)")]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Input file or directory
-    #[arg(short, long, value_name = "PATH")]
-    pub input: PathBuf,
+    #[arg(short, long, value_name = "PATH", required = true)]
+    pub input: Option<PathBuf>,
 
     #[arg(short, long)]
     pub output: Option<PathBuf>,
@@ -27,43 +35,323 @@ pub struct Cli {
     #[arg(long, value_enum, default_value_t = JsonMode::List)]
     pub json_mode: JsonMode,
 
+    #[arg(long, value_enum, default_value_t = YamlMode::Flat)]
+    pub yaml_mode: YamlMode,
+
+    #[arg(long, value_enum, default_value_t = NdjsonMode::Entry)]
+    pub ndjson_mode: NdjsonMode,
+
+    #[arg(long, value_enum, default_value_t = CsvTable::Permissions)]
+    pub csv_table: CsvTable,
+
+    /// Write `true`/`false` and numbers as JSON booleans and numbers (json format)
     #[arg(long)]
-    pub include_attributes: bool,
+    pub typed: bool,
+
+    /// With --typed, keep values under this element name or path pattern as strings;
+    /// repeatable
+    #[arg(
+        long,
+        value_name = "ELEM|PATTERN",
+        default_value = "fullName",
+        requires = "typed"
+    )]
+    pub keep_string: Vec<String>,
 
     #[arg(long)]
+    pub include_attributes: bool,
+
+    /// How namespaced names are written: `strip` (local names), `prefix` (as written,
+    /// with the prefix map listed once per document) or `clark` (`{uri}local`)
+    #[arg(long, value_enum, default_value_t = NsMode::Strip)]
+    pub ns_mode: NsMode,
+
+    /// Same as `--ns-mode prefix`
+    #[arg(long, conflicts_with = "ns_mode")]
     pub keep_ns_prefix: bool,
 
+    /// Keep comments (`#comment`), processing instructions (`#pi`) and the doctype
+    /// (`#doctype`) as entries
+    #[arg(long)]
+    pub include_comments: bool,
+
+    /// Cut text values longer than N (0 = unlimited), ending them with `…[+N chars]`
     #[arg(long, default_value_t = 2048)]
     pub max_text_len: usize,
 
+    /// Unit of --max-text-len (`tokens` uses --tokenizer)
+    #[arg(long, value_enum, default_value_t = TruncateBy::Chars)]
+    pub truncate_by: TruncateBy,
+
+    /// Never truncate values under this element name or path pattern; repeatable
+    #[arg(
+        long,
+        value_name = "ELEM|PATTERN",
+        default_value = "errorConditionFormula"
+    )]
+    pub never_truncate: Vec<String>,
+
+    /// Keep leading/trailing and blank-only whitespace in the text of this element
+    /// (and its descendants), e.g. `htmlValue`; repeatable
+    #[arg(long, value_name = "ELEM")]
+    pub preserve_space: Vec<String>,
+
     #[arg(long, value_enum, default_value_t = PathStyle::Slash)]
     pub path_style: PathStyle,
 
     #[arg(long)]
     pub always_wrap: bool,
 
+    /// Keep only entries under a path pattern (`/Profile/fieldPermissions[*]/field`;
+    /// `*` = any element, `**` = any depth, no index = any index); repeatable
+    #[arg(long, value_name = "PATTERN")]
+    pub select: Vec<String>,
+
+    /// Remove entries under a path pattern; repeatable
+    #[arg(long, value_name = "PATTERN")]
+    pub drop: Vec<String>,
+
+    /// Keep only records whose child matches (`field~^Account\.`, `type=Lookup`,
+    /// `enabled!=false`); other records of the same shape are dropped whole; repeatable
+    #[arg(long = "where", value_name = "EXPR")]
+    pub where_: Vec<String>,
+
+    /// Record the source line of every entry (`L<n>` prefix in TOON/Compact, `l` in JSON)
+    #[arg(long)]
+    pub with_locations: bool,
+
     #[arg(short = 't', long)]
     pub show_tokens: bool,
 
     #[arg(long)]
     pub show_tokens_per_file: bool,
+
+    /// Shrink the output until it fits N tokens (shorter values, no descriptions,
+    /// collapsed low-value sections, then whole files dropped), noting what was cut
+    #[arg(long, value_name = "N")]
+    pub max_tokens: Option<usize>,
+
+    /// Split the output into numbered files of at most N tokens each, plus a
+    /// manifest.json; a component is only split when it alone exceeds N
+    #[arg(
+        long,
+        value_name = "N",
+        requires = "output_dir",
+        conflicts_with_all = ["max_tokens", "output"]
+    )]
+    pub chunk_tokens: Option<usize>,
+
+    /// Write one rendered file per input into DIR, mirroring the input tree
+    /// (`Account.object-meta.xml` -> `Account.object.toon`); with --chunk-tokens,
+    /// the chunk files go there instead
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["output", "max_tokens"])]
+    pub output_dir: Option<PathBuf>,
+
+    /// Render each input into --output-dir, then keep those files up to date as
+    /// the inputs change, until interrupted
+    #[arg(
+        long,
+        requires = "output_dir",
+        conflicts_with_all = ["max_tokens", "output", "show_tokens", "show_tokens_per_file"]
+    )]
+    pub watch: bool,
+
+    /// Don't read or write the cache of flattened files
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// Where unchanged files are cached between runs
+    #[arg(
+        long,
+        value_name = "DIR",
+        default_value = ".sfmeta-cache",
+        conflicts_with = "no_cache"
+    )]
+    pub cache_dir: PathBuf,
+
+    /// Tokenizer used for --show-tokens, --show-tokens-per-file, --max-tokens and --chunk-tokens
+    #[arg(long, value_enum, default_value_t = Tokenizer::Approx)]
+    pub tokenizer: Tokenizer,
+
+    /// Skip files that fail to parse and report them after the output
+    #[arg(long)]
+    pub keep_going: bool,
+
+    /// Apply the `[profile.NAME]` settings of the project's sfmeta-reader.toml
+    #[arg(long, value_name = "NAME")]
+    pub profile: Option<String>,
+
+    /// With --keep-going, exit non-zero if any file was skipped
+    #[arg(long, requires = "keep_going")]
+    pub fail_on_error: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Rebuild Metadata API XML from TOON, Compact or JSON list output
+    ToXml(ToXmlArgs),
+    /// Edit an XML file in place with set/insert/delete operations on flattened paths
+    Patch(PatchArgs),
+    /// Print the parts of metadata files matched by an XPath-like expression
+    Query(QueryArgs),
+    /// Write a whole project into a SQLite database for offline SQL analysis
+    ExportSqlite(ExportSqliteArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct ToXmlArgs {
+    /// Rendered file to convert (reads stdin when omitted)
+    #[arg(value_name = "FILE")]
+    pub input: Option<PathBuf>,
+
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Format of the input; `auto` sniffs it from the content
+    #[arg(long, value_enum, default_value_t = SourceFormat::Auto)]
+    pub from: SourceFormat,
+
+    /// Write each wrapped document back to the file path recorded in the wrapper
+    #[arg(long, conflicts_with = "output")]
+    pub in_place: bool,
+
+    /// With --in-place, write even when the input lost content the files have
+    #[arg(long, requires = "in_place")]
+    pub force: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct PatchArgs {
+    /// XML file to edit
+    #[arg(value_name = "FILE")]
+    pub file: PathBuf,
+
+    /// File with one operation per line (`-` for stdin)
+    #[arg(long, value_name = "FILE")]
+    pub ops: Option<PathBuf>,
+
+    /// A single operation, e.g. `set /Profile[0]/custom[0]/#text = true` (repeatable)
+    #[arg(short = 'e', long = "op", value_name = "OP")]
+    pub op: Vec<String>,
+
+    /// Write the result here instead of editing FILE
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Print the patched XML instead of writing it
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct ExportSqliteArgs {
+    /// Project directory (searched recursively) or a single file
+    #[arg(value_name = "PATH")]
+    pub input: PathBuf,
+
+    /// Database file; existing export tables in it are replaced
+    #[arg(short, long, value_name = "DB")]
+    pub output: PathBuf,
+
+    #[arg(long, default_value = "*.xml")]
+    pub glob: String,
+
+    /// Skip files that fail to parse instead of stopping
+    #[arg(long)]
+    pub keep_going: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct QueryArgs {
+    /// Expression such as `//fields[type="Lookup"]/referenceTo` or
+    /// `/Profile/fieldPermissions[field="Account.Rating"]`
+    #[arg(value_name = "XPATH")]
+    pub expr: String,
+
+    /// Input file or directory
+    #[arg(value_name = "PATH")]
+    pub input: PathBuf,
+
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    #[arg(long, default_value = "*.xml")]
+    pub glob: String,
+
+    #[arg(short, long, value_enum, default_value_t = Format::Toon)]
+    pub format: Format,
+
+    #[arg(long)]
+    pub minify: bool,
+
+    #[arg(long, value_enum, default_value_t = JsonMode::List)]
+    pub json_mode: JsonMode,
+
+    #[arg(long, value_enum, default_value_t = YamlMode::Flat)]
+    pub yaml_mode: YamlMode,
+
+    #[arg(long, value_enum, default_value_t = NdjsonMode::Entry)]
+    pub ndjson_mode: NdjsonMode,
+
+    #[arg(long, value_enum, default_value_t = CsvTable::Permissions)]
+    pub csv_table: CsvTable,
+
+    /// Include attributes in the output (attribute steps and predicates work either way)
+    #[arg(long)]
+    pub include_attributes: bool,
+
+    #[arg(long, value_enum, default_value_t = PathStyle::Slash)]
+    pub path_style: PathStyle,
+
+    /// Record the source line of every entry
+    #[arg(long)]
+    pub with_locations: bool,
+
+    /// Skip files that fail to parse instead of stopping
+    #[arg(long)]
+    pub keep_going: bool,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
-pub enum Format {
+pub enum SourceFormat {
+    Auto,
     Json,
     Toon,
     Compact,
+    Ndjson,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
-pub enum JsonMode {
-    List,
-    Map,
+pub enum Format {
+    Json,
+    Toon,
+    Compact,
+    Yaml,
+    /// JSON Lines, streamed as files are read
+    Ndjson,
+    /// Human-readable report with tables
+    Markdown,
+    /// Permission or field inventory rows (see --csv-table)
+    Csv,
+}
+
+impl Format {
+    /// File extension for output files written in this format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Toon => "toon",
+            Format::Compact => "txt",
+            Format::Yaml => "yaml",
+            Format::Ndjson => "ndjson",
+            Format::Markdown => "md",
+            Format::Csv => "csv",
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
-pub enum PathStyle {
-    Slash,
-    Dot,
+pub enum TruncateBy {
+    Chars,
+    Tokens,
 }
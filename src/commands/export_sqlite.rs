@@ -0,0 +1,289 @@
+use crate::cli::{ExportSqliteArgs, NsMode, PathStyle};
+use crate::parser::{SalesforceMetadata, parse_salesforce_xml};
+use crate::util::{FileError, file_name_stem, object_dir_name, walk_inputs};
+use crate::xml::tree::Node;
+use crate::xml::{FlatEntry, FlattenOptions, build_document, flatten_xml_file};
+use anyhow::{Context, Result};
+use rusqlite::{Connection, Transaction, params};
+use std::path::Path;
+
+/// Tables are dropped and recreated, so re-running an export replaces its data.
+const SCHEMA: &str = "
+DROP TABLE IF EXISTS flow_elements;
+DROP TABLE IF EXISTS flows;
+DROP TABLE IF EXISTS field_permissions;
+DROP TABLE IF EXISTS object_permissions;
+DROP TABLE IF EXISTS fields;
+DROP TABLE IF EXISTS flat_entries;
+DROP TABLE IF EXISTS metadata_components;
+DROP TABLE IF EXISTS files;
+
+CREATE TABLE files (
+    id INTEGER PRIMARY KEY,
+    path TEXT NOT NULL UNIQUE,
+    size INTEGER NOT NULL
+);
+CREATE TABLE metadata_components (
+    id INTEGER PRIMARY KEY,
+    file_id INTEGER NOT NULL REFERENCES files(id),
+    type TEXT NOT NULL,
+    name TEXT NOT NULL,
+    label TEXT
+);
+CREATE TABLE flat_entries (
+    file_id INTEGER NOT NULL REFERENCES files(id),
+    seq INTEGER NOT NULL,
+    path TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    value TEXT NOT NULL,
+    line INTEGER,
+    PRIMARY KEY (file_id, seq)
+);
+CREATE TABLE fields (
+    component_id INTEGER NOT NULL REFERENCES metadata_components(id),
+    object TEXT NOT NULL,
+    name TEXT NOT NULL,
+    type TEXT,
+    label TEXT,
+    is_required INTEGER NOT NULL,
+    is_unique INTEGER NOT NULL,
+    length INTEGER,
+    description TEXT
+);
+CREATE TABLE object_permissions (
+    component_id INTEGER NOT NULL REFERENCES metadata_components(id),
+    object TEXT NOT NULL,
+    allow_create INTEGER NOT NULL,
+    allow_read INTEGER NOT NULL,
+    allow_edit INTEGER NOT NULL,
+    allow_delete INTEGER NOT NULL
+);
+CREATE TABLE field_permissions (
+    component_id INTEGER NOT NULL REFERENCES metadata_components(id),
+    object TEXT NOT NULL,
+    field TEXT NOT NULL,
+    readable INTEGER NOT NULL,
+    editable INTEGER NOT NULL
+);
+CREATE TABLE flows (
+    id INTEGER PRIMARY KEY,
+    component_id INTEGER NOT NULL REFERENCES metadata_components(id),
+    name TEXT NOT NULL,
+    label TEXT,
+    process_type TEXT,
+    status TEXT
+);
+CREATE TABLE flow_elements (
+    flow_id INTEGER NOT NULL REFERENCES flows(id),
+    element_type TEXT NOT NULL,
+    name TEXT NOT NULL,
+    label TEXT,
+    target TEXT
+);
+CREATE INDEX flat_entries_path ON flat_entries(path);
+CREATE INDEX metadata_components_type ON metadata_components(type);
+";
+
+pub fn run(args: &ExportSqliteArgs) -> Result<()> {
+    let inputs = walk_inputs(&args.input, true, &args.glob).with_context(|| {
+        format!(
+            "Failed to enumerate input path(s): {}",
+            args.input.display()
+        )
+    })?;
+    if inputs.is_empty() {
+        anyhow::bail!(
+            "No input files matched (input={}, glob={})",
+            args.input.display(),
+            args.glob
+        );
+    }
+
+    // Everything is kept: attributes, full text values and source lines.
+    let opts = FlattenOptions {
+        include_attributes: true,
+        include_text: true,
+        include_cdata: true,
+        ns_mode: NsMode::Strip,
+        max_text_len: 0,
+        truncate_tokenizer: None,
+        never_truncate: Vec::new(),
+        path_style: PathStyle::Slash,
+        with_locations: true,
+        include_comments: false,
+        preserve_space: Vec::new(),
+    };
+
+    let mut db = Connection::open(&args.output)
+        .with_context(|| format!("Failed to open database: {}", args.output.display()))?;
+    let tx = db.transaction()?;
+    tx.execute_batch(SCHEMA)?;
+
+    let mut errors: Vec<FileError> = Vec::new();
+    let mut exported = 0;
+    for path in inputs {
+        match export_file(&tx, &path, &opts) {
+            Ok(()) => exported += 1,
+            Err(err) if args.keep_going => errors.push(FileError::from_error(path, &err)),
+            Err(err) => return Err(err),
+        }
+    }
+    tx.commit()?;
+
+    eprintln!("Exported {exported} file(s) to {}", args.output.display());
+    for e in &errors {
+        eprintln!("skipped {e}");
+    }
+    Ok(())
+}
+
+fn export_file(tx: &Transaction<'_>, path: &Path, opts: &FlattenOptions) -> Result<()> {
+    let entries = flatten_xml_file(path, opts)
+        .with_context(|| format!("XML flatten failed: {}", path.display()))?;
+    let xml = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read input file: {}", path.display()))?;
+    let metadata = parse_salesforce_xml(&xml, true)
+        .with_context(|| format!("XML parse failed: {}", path.display()))?;
+
+    tx.execute(
+        "INSERT INTO files (path, size) VALUES (?1, ?2)",
+        params![path.to_string_lossy(), xml.len()],
+    )?;
+    let file_id = tx.last_insert_rowid();
+    insert_entries(tx, file_id, &entries)?;
+
+    let doc = build_document(&entries)?;
+    let Some(root) = doc.elements().next() else {
+        return Ok(());
+    };
+    let name = root
+        .child_text("fullName")
+        .unwrap_or_else(|| file_name_stem(path));
+    tx.execute(
+        "INSERT INTO metadata_components (file_id, type, name, label) VALUES (?1, ?2, ?3, ?4)",
+        params![file_id, root.name, name, root.child_text("label")],
+    )?;
+    let component_id = tx.last_insert_rowid();
+
+    // A source-format field file belongs to the object folder it sits in.
+    let object = match root.name.as_str() {
+        "CustomField" => object_dir_name(path),
+        _ => name.clone(),
+    };
+    insert_structured(tx, component_id, &object, &metadata)?;
+
+    if root.name == "Flow" {
+        insert_flow(tx, component_id, &name, root)?;
+    }
+    Ok(())
+}
+
+fn insert_entries(tx: &Transaction<'_>, file_id: i64, entries: &[FlatEntry]) -> Result<()> {
+    let mut stmt = tx.prepare_cached(
+        "INSERT INTO flat_entries (file_id, seq, path, kind, value, line)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?;
+    for (seq, e) in entries.iter().enumerate() {
+        stmt.execute(params![file_id, seq, e.p, format!("{:?}", e.k), e.v, e.l])?;
+    }
+    Ok(())
+}
+
+/// Fields and permissions, as read by the structured parser.
+fn insert_structured(
+    tx: &Transaction<'_>,
+    component_id: i64,
+    object: &str,
+    metadata: &SalesforceMetadata,
+) -> Result<()> {
+    let mut stmt = tx.prepare_cached(
+        "INSERT INTO fields
+         (component_id, object, name, type, label, is_required, is_unique, length, description)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+    )?;
+    for f in &metadata.fields {
+        stmt.execute(params![
+            component_id,
+            object,
+            f.name,
+            f.field_type,
+            f.label,
+            f.required,
+            f.unique,
+            f.length,
+            f.description
+        ])?;
+    }
+
+    let (object_perms, field_perms) = match (&metadata.profile, &metadata.permission_set) {
+        (Some(p), _) => (&p.object_permissions, &p.field_permissions),
+        (None, Some(ps)) => (&ps.object_permissions, &ps.field_permissions),
+        (None, None) => return Ok(()),
+    };
+    let mut stmt = tx.prepare_cached(
+        "INSERT INTO object_permissions
+         (component_id, object, allow_create, allow_read, allow_edit, allow_delete)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?;
+    for p in object_perms {
+        stmt.execute(params![
+            component_id,
+            p.object,
+            p.allow_create,
+            p.allow_read,
+            p.allow_edit,
+            p.allow_delete
+        ])?;
+    }
+    let mut stmt = tx.prepare_cached(
+        "INSERT INTO field_permissions (component_id, object, field, readable, editable)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?;
+    for p in field_perms {
+        let (object, field) = p.field.split_once('.').unwrap_or(("", &p.field));
+        stmt.execute(params![component_id, object, field, p.readable, p.editable])?;
+    }
+    Ok(())
+}
+
+/// The flow row, plus one row per named element (decisions, assignments, screens,
+/// record operations, ...) with the first connector it points to.
+fn insert_flow(tx: &Transaction<'_>, component_id: i64, name: &str, root: &Node) -> Result<()> {
+    tx.execute(
+        "INSERT INTO flows (component_id, name, label, process_type, status)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            component_id,
+            name,
+            root.child_text("label"),
+            root.child_text("processType"),
+            root.child_text("status")
+        ],
+    )?;
+    let flow_id = tx.last_insert_rowid();
+
+    let mut stmt = tx.prepare_cached(
+        "INSERT INTO flow_elements (flow_id, element_type, name, label, target)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?;
+    for n in root.elements() {
+        let Some(element_name) = n.child_text("name") else {
+            continue;
+        };
+        if n.elements().next().is_none() {
+            continue;
+        }
+        let target = n
+            .elements()
+            .filter(|c| c.name == "connector" || c.name == "defaultConnector")
+            .find_map(|c| c.child_text("targetReference"));
+        stmt.execute(params![
+            flow_id,
+            n.name,
+            element_name,
+            n.child_text("label"),
+            target
+        ])?;
+    }
+    Ok(())
+}
@@ -0,0 +1,4 @@
+pub mod export_sqlite;
+pub mod patch;
+pub mod query;
+pub mod to_xml;
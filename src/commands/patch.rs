@@ -0,0 +1,42 @@
+use crate::cli::PatchArgs;
+use crate::patch::{apply_ops, parse_ops};
+use anyhow::{Context, Result};
+use std::io::Read;
+
+pub fn run(args: &PatchArgs) -> Result<()> {
+    let xml = std::fs::read_to_string(&args.file)
+        .with_context(|| format!("Failed to read XML file: {}", args.file.display()))?;
+
+    let mut text = String::new();
+    match args.ops.as_deref() {
+        Some(p) if p.as_os_str() == "-" => {
+            std::io::stdin()
+                .read_to_string(&mut text)
+                .context("Failed to read patch operations from stdin")?;
+        }
+        Some(p) => {
+            text = std::fs::read_to_string(p)
+                .with_context(|| format!("Failed to read patch file: {}", p.display()))?;
+        }
+        None => {}
+    }
+    for op in &args.op {
+        text.push('\n');
+        text.push_str(op);
+    }
+
+    let ops = parse_ops(&text)?;
+    if ops.is_empty() {
+        anyhow::bail!("No patch operations given (use --ops FILE or -e OP)");
+    }
+    let patched = apply_ops(&xml, &ops)?;
+
+    match (&args.output, args.dry_run) {
+        (_, true) => print!("{patched}"),
+        (Some(out_path), false) => std::fs::write(out_path, patched)
+            .with_context(|| format!("Failed to write output file: {}", out_path.display()))?,
+        (None, false) => std::fs::write(&args.file, patched)
+            .with_context(|| format!("Failed to write XML file: {}", args.file.display()))?,
+    }
+    Ok(())
+}
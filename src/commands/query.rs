@@ -0,0 +1,104 @@
+use crate::cli::{Format, NsMode, QueryArgs};
+use crate::query::{self, Query};
+use crate::render::{
+    Doc, Trailer, render_compact, render_csv, render_json, render_markdown, render_ndjson,
+    render_toon, render_yaml,
+};
+use crate::util::{FileError, walk_inputs};
+use crate::xml::{FlatEntry, FlattenOptions, ValueKind, flatten_xml_file};
+use anyhow::{Context, Result};
+use std::path::Path;
+
+pub fn run(args: &QueryArgs) -> Result<()> {
+    let query = Query::parse(&args.expr)?;
+    // Attributes are always read so `@attr` steps and predicates can see them.
+    let opts = FlattenOptions {
+        include_attributes: true,
+        include_text: true,
+        include_cdata: true,
+        ns_mode: NsMode::Strip,
+        max_text_len: 0,
+        truncate_tokenizer: None,
+        never_truncate: Vec::new(),
+        path_style: args.path_style,
+        with_locations: args.with_locations,
+        include_comments: false,
+        preserve_space: Vec::new(),
+    };
+    let wants_attrs = args.include_attributes || query.selects_attrs();
+
+    let inputs = walk_inputs(&args.input, true, &args.glob).with_context(|| {
+        format!(
+            "Failed to enumerate input path(s): {}",
+            args.input.display()
+        )
+    })?;
+
+    let mut docs: Vec<Doc> = Vec::new();
+    for path in inputs {
+        let entries = match flatten_xml_file(&path, &opts) {
+            Ok(entries) => entries,
+            Err(err) if args.keep_going => {
+                eprintln!("skipped {}", FileError::from_error(path, &err));
+                continue;
+            }
+            Err(err) => {
+                return Err(err.context(format!("XML flatten failed: {}", path.display())));
+            }
+        };
+        let mut matched = query::select(&entries, &query)?;
+        if !wants_attrs {
+            matched.retain(|e| !matches!(e.k, ValueKind::Attr));
+        }
+        if matched.is_empty() {
+            continue;
+        }
+        let body = render(&path, &matched, args)?;
+        docs.push(Doc {
+            path,
+            entries: matched,
+            body,
+        });
+    }
+
+    if docs.is_empty() {
+        eprintln!("No matches for {}", args.expr);
+        return Ok(());
+    }
+
+    let text = if docs.len() == 1 {
+        docs.swap_remove(0).body
+    } else {
+        let trailer = Trailer::default();
+        match args.format {
+            Format::Json => {
+                render_json::wrap_many(&docs, trailer, args.json_mode, args.minify, None)?
+            }
+            Format::Toon => render_toon::wrap_many(&docs, trailer),
+            Format::Compact => render_compact::wrap_many(&docs, trailer),
+            Format::Yaml => render_yaml::wrap_many(&docs, trailer, args.yaml_mode)?,
+            Format::Ndjson => render_ndjson::wrap_many(&docs, trailer)?,
+            Format::Markdown => render_markdown::wrap_many(&docs, trailer),
+            Format::Csv => render_csv::wrap_many(&docs),
+        }
+    };
+
+    match &args.output {
+        Some(out_path) => std::fs::write(out_path, text)
+            .with_context(|| format!("Failed to write output file: {}", out_path.display()))?,
+        None => print!("{text}"),
+    }
+    Ok(())
+}
+
+fn render(path: &Path, entries: &[FlatEntry], args: &QueryArgs) -> Result<String> {
+    Ok(match args.format {
+        Format::Json => render_json::render(entries, args.json_mode, args.minify, None)?,
+        Format::Toon => render_toon::render(entries),
+        Format::Compact => render_compact::render(entries),
+        Format::Yaml => render_yaml::render(entries, args.yaml_mode)?,
+        Format::Ndjson => render_ndjson::render(path, entries, args.ndjson_mode, None)?,
+        Format::Markdown => render_markdown::render(path, entries)?,
+        Format::Csv => render_csv::render(path, entries, args.csv_table)?,
+    })
+}
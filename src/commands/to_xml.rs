@@ -0,0 +1,241 @@
+use crate::cli::{SourceFormat, ToXmlArgs};
+use crate::render::{Parsed, ParsedDoc, render_compact, render_json, render_ndjson, render_toon};
+use crate::util::is_truncated;
+use crate::xml::tree::Child;
+use crate::xml::{
+    FlatEntry, FlattenOptions, NsMode, ValueKind, build_document, flatten_xml_str, write_document,
+};
+use anyhow::{Context, Result, bail};
+use quick_xml::Reader;
+use quick_xml::events::{BytesStart, Event};
+use std::io::Read;
+use std::path::Path;
+
+pub fn run(args: &ToXmlArgs) -> Result<()> {
+    let text = match &args.input {
+        Some(path) => std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read input file: {}", path.display()))?,
+        None => {
+            let mut s = String::new();
+            std::io::stdin()
+                .read_to_string(&mut s)
+                .context("Failed to read stdin")?;
+            s
+        }
+    };
+
+    let from = match args.from {
+        SourceFormat::Auto => sniff_format(&text),
+        f => f,
+    };
+    let Parsed { docs, reduced } = match from {
+        SourceFormat::Json => render_json::parse(&text)?,
+        SourceFormat::Toon => render_toon::parse(&text)?,
+        SourceFormat::Ndjson => render_ndjson::parse(&text)?,
+        SourceFormat::Compact | SourceFormat::Auto => render_compact::parse(&text)?,
+    };
+
+    if args.in_place {
+        if reduced && !args.force {
+            bail!(
+                "Input was reduced by --max-tokens, so writing it back would lose content; \
+                 re-render without --max-tokens, or pass --force to write it anyway"
+            );
+        }
+        // Check every document before writing any, so a refusal leaves all files as they were.
+        let mut files = Vec::with_capacity(docs.len());
+        for doc in &docs {
+            let Some(file) = &doc.file else {
+                bail!("--in-place needs wrapped output that records each source file");
+            };
+            if !args.force {
+                check_lossless(doc, file, from)?;
+            }
+            files.push(file.clone());
+        }
+        for (doc, file) in docs.into_iter().zip(files) {
+            let xml = to_xml(&doc.entries)
+                .with_context(|| format!("Failed to rebuild XML for {}", file.display()))?;
+            std::fs::write(&file, xml)
+                .with_context(|| format!("Failed to write output file: {}", file.display()))?;
+        }
+        return Ok(());
+    }
+
+    let doc = match docs.as_slice() {
+        [doc] => doc,
+        [] => bail!("Input holds no entries to convert"),
+        _ => bail!(
+            "Input holds {} documents; use --in-place to write each back to its file",
+            docs.len()
+        ),
+    };
+    let xml = to_xml(&doc.entries)?;
+
+    match &args.output {
+        Some(out_path) => std::fs::write(out_path, xml)
+            .with_context(|| format!("Failed to write output file: {}", out_path.display()))?,
+        None => print!("{xml}"),
+    }
+    Ok(())
+}
+
+/// Refuse to overwrite `file` with entries that lost something its current
+/// content has: values cut by `--max-text-len`, attributes, namespace
+/// declarations or comments left out at render time, or empty elements, which no
+/// entry can describe. The Metadata API default namespace is always implied. TOON
+/// does not escape backslashes, so a literal `\n` or `\r` in the file would come
+/// back as a line break.
+fn check_lossless(doc: &ParsedDoc, path: &Path, from: SourceFormat) -> Result<()> {
+    let file = path.display();
+    let force = "or pass --force to write it anyway";
+    if let Some(e) = doc.entries.iter().find(|e| is_truncated(&e.v)) {
+        bail!(
+            "{file}: {} was truncated; re-render with --max-text-len 0, {force}",
+            e.p
+        );
+    }
+
+    let xml = match std::fs::read_to_string(path) {
+        Ok(xml) => xml,
+        // A new file has nothing to lose.
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err).with_context(|| format!("Failed to read {file}")),
+    };
+    let opts = FlattenOptions::builder()
+        .include_attributes(true)
+        .ns_mode(NsMode::Prefix)
+        .include_comments(true)
+        .max_text_len(0)
+        .build()?;
+    let source = flatten_xml_str(&xml, &opts).with_context(|| format!("Failed to read {file}"))?;
+
+    if from == SourceFormat::Toon
+        && let Some(e) = source
+            .iter()
+            .find(|e| e.v.contains("\\n") || e.v.contains("\\r"))
+    {
+        bail!(
+            "{file}: {} contains a literal `\\n` or `\\r`, which TOON reads back as a line break; \
+             re-render with --format json, {force}",
+            e.p
+        );
+    }
+
+    let lost = |kinds: fn(&ValueKind) -> bool| {
+        source.iter().any(|e| kinds(&e.k)) && !doc.entries.iter().any(|e| kinds(&e.k))
+    };
+    if lost(|k| matches!(k, ValueKind::Attr)) {
+        bail!(
+            "{file} has attributes, but the input has no attribute entries; \
+             re-render with --include-attributes, {force}"
+        );
+    }
+    if lost(|k| matches!(k, ValueKind::Namespace)) {
+        bail!(
+            "{file} declares namespace prefixes, but the input lists none; \
+             re-render with --ns-mode prefix or clark, {force}"
+        );
+    }
+    if lost(|k| matches!(k, ValueKind::Comment | ValueKind::Instruction)) {
+        bail!(
+            "{file} has comments, but the input has none; re-render with --include-comments, {force}"
+        );
+    }
+    if let Some(path) = first_empty_element(&xml)? {
+        bail!("{file}: {path} is empty, and entries cannot describe empty elements; {force}");
+    }
+    Ok(())
+}
+
+/// Path of the first element with no text, children, comments or attributes
+/// (namespace declarations aside). Flattening leaves no entry for it, so it would
+/// disappear on the way back.
+fn first_empty_element(xml: &str) -> Result<Option<String>> {
+    let mut reader = Reader::from_str(xml);
+    // Element names and whether each has content so far.
+    let mut stack: Vec<(String, bool)> = Vec::new();
+    let path = |stack: &[(String, bool)], name: &str| {
+        let mut p: String = stack.iter().map(|(n, _)| format!("/{n}")).collect();
+        p.push('/');
+        p.push_str(name);
+        p
+    };
+    let has_attrs = |e: &BytesStart<'_>| {
+        e.attributes()
+            .with_checks(false)
+            .flatten()
+            .any(|a| a.key.as_namespace_binding().is_none())
+    };
+
+    loop {
+        let event = reader.read_event()?;
+        if !matches!(event, Event::End(_) | Event::Eof)
+            && let Some((_, content)) = stack.last_mut()
+        {
+            *content |= match &event {
+                Event::Text(t) => !t.iter().all(u8::is_ascii_whitespace),
+                _ => true,
+            };
+        }
+        match event {
+            Event::Start(e) => {
+                let name = String::from_utf8_lossy(e.name().as_ref()).into_owned();
+                let content = has_attrs(&e);
+                stack.push((name, content));
+            }
+            Event::Empty(e) if !has_attrs(&e) => {
+                return Ok(Some(path(
+                    &stack,
+                    &String::from_utf8_lossy(e.name().as_ref()),
+                )));
+            }
+            Event::End(_) => {
+                if let Some((name, false)) = stack.pop() {
+                    return Ok(Some(path(&stack, &name)));
+                }
+            }
+            Event::Eof => return Ok(None),
+            _ => {}
+        }
+    }
+}
+
+fn to_xml(entries: &[FlatEntry]) -> Result<String> {
+    let doc = build_document(entries)?;
+    match doc
+        .children
+        .iter()
+        .filter(|c| matches!(c, Child::Element(_)))
+        .count()
+    {
+        1 => write_document(&doc),
+        0 => bail!("No elements to write"),
+        n => bail!("Entries describe {n} root elements; expected one"),
+    }
+}
+
+/// Guess the rendered format: NDJSON lines start with `{"file"` (JSON wrappers
+/// sort `file` after `data`), JSON starts with a bracket, TOON separates path and
+/// value with ` = ` (paths never contain spaces), Compact with a bare `=`.
+fn sniff_format(text: &str) -> SourceFormat {
+    let trimmed = text.trim_start();
+    if trimmed.starts_with(r#"{"file""#) {
+        return SourceFormat::Ndjson;
+    }
+    if trimmed.starts_with('[') || trimmed.starts_with('{') {
+        return SourceFormat::Json;
+    }
+    if trimmed.starts_with("### ") {
+        return SourceFormat::Toon;
+    }
+    if trimmed.starts_with("file=") {
+        return SourceFormat::Compact;
+    }
+
+    let first = trimmed.lines().next().unwrap_or_default();
+    match first.find('=') {
+        Some(i) if first[..i].ends_with(' ') => SourceFormat::Toon,
+        _ => SourceFormat::Compact,
+    }
+}
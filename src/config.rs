@@ -0,0 +1,197 @@
+//! Project defaults from `sfmeta-reader.toml`.
+//!
+//! The file is looked up from the input's directory upward, stopping at the
+//! Salesforce project root (the directory with `sfdx-project.json`). Top-level keys
+//! are long option names (`format = "json"`, `max-text-len = 0`, `select = [...]`);
+//! a `[profile.NAME]` table, chosen with `--profile NAME`, overrides them. Options
+//! given on the command line win over both; config values that would conflict
+//! with them, or that require an option left out that way, are dropped.
+
+use crate::cli::Cli;
+use anyhow::{Context, Result, bail};
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, Parser};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+
+pub const FILE_NAME: &str = "sfmeta-reader.toml";
+
+/// Parse the command line, with defaults from the project's config file.
+pub fn parse() -> Result<Cli> {
+    let args: Vec<OsString> = std::env::args_os().collect();
+    // A lenient first pass finds the input, the profile, and what was given explicitly.
+    let first = Cli::command().ignore_errors(true).get_matches_from(&args);
+    let profile = first.get_one::<String>("profile");
+
+    // Subcommands have their own options.
+    let config = match first.get_one::<PathBuf>("input") {
+        Some(input) if first.subcommand().is_none() => discover(input),
+        _ => None,
+    };
+    let Some(config) = config else {
+        if let Some(name) = profile {
+            bail!("--profile {name}: no {FILE_NAME} found for the input");
+        }
+        return Ok(Cli::parse_from(args));
+    };
+
+    let defaults = load(&config, profile.map(String::as_str))?;
+    let mut merged = vec![args[0].clone()];
+    merged.extend(
+        config_args(&defaults, &first, &args)
+            .with_context(|| format!("Invalid config: {}", config.display()))?
+            .into_iter()
+            .map(OsString::from),
+    );
+    merged.extend(args.into_iter().skip(1));
+    Ok(Cli::parse_from(merged))
+}
+
+/// The nearest `sfmeta-reader.toml` at or above `input`, within its project.
+fn discover(input: &Path) -> Option<PathBuf> {
+    let start = if input.is_dir() {
+        input
+    } else {
+        input.parent()?
+    };
+    let start = start
+        .canonicalize()
+        .or_else(|_| std::env::current_dir())
+        .ok()?;
+    for dir in start.ancestors() {
+        let file = dir.join(FILE_NAME);
+        if file.is_file() {
+            return Some(file);
+        }
+        if dir.join("sfdx-project.json").is_file() {
+            break;
+        }
+    }
+    None
+}
+
+/// Top-level settings with the chosen profile's settings over them.
+fn load(path: &Path, profile: Option<&str>) -> Result<Table> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config: {}", path.display()))?;
+    let mut table: Table = text
+        .parse()
+        .with_context(|| format!("Invalid config: {}", path.display()))?;
+
+    let profiles = match table.remove("profile") {
+        Some(Value::Table(t)) => t,
+        Some(_) => bail!(
+            "Invalid config: {}: `profile` must be a table",
+            path.display()
+        ),
+        None => Table::new(),
+    };
+    if let Some(name) = profile {
+        let Some(Value::Table(overrides)) = profiles.get(name) else {
+            let known: Vec<&str> = profiles.keys().map(String::as_str).collect();
+            bail!(
+                "--profile {name}: no [profile.{name}] in {} (profiles: {})",
+                path.display(),
+                if known.is_empty() {
+                    "none".to_string()
+                } else {
+                    known.join(", ")
+                }
+            );
+        };
+        table.extend(overrides.clone());
+    }
+    Ok(table)
+}
+
+/// `--name=value` arguments for every setting not given on the command line.
+/// Settings that conflict with an explicit option, either way round, are left
+/// out, and so are settings whose required options end up missing.
+fn config_args(table: &Table, given: &ArgMatches, args: &[OsString]) -> Result<Vec<String>> {
+    let cmd = Cli::command();
+    let explicit: Vec<&clap::Arg> = cmd
+        .get_arguments()
+        .filter(|a| given.value_source(a.get_id().as_str()) == Some(ValueSource::CommandLine))
+        .collect();
+    let conflicts = |a: &clap::Arg, b: &clap::Arg| {
+        cmd.get_arg_conflicts_with(a)
+            .iter()
+            .any(|c| c.get_id() == b.get_id())
+    };
+
+    let mut settings: Vec<Vec<String>> = Vec::new();
+    for (key, value) in table {
+        let long = key.replace('_', "-");
+        let Some(arg) = cmd
+            .get_arguments()
+            .find(|a| a.get_long() == Some(long.as_str()))
+        else {
+            bail!("unknown option `{key}`");
+        };
+        if matches!(long.as_str(), "input" | "profile" | "help") {
+            bail!("`{key}` cannot be set in the config file");
+        }
+        let overridden = explicit
+            .iter()
+            .any(|e| e.get_id() == arg.get_id() || conflicts(e, arg) || conflicts(arg, e));
+        if overridden {
+            continue;
+        }
+
+        let items = match value {
+            Value::Array(items) => items.as_slice(),
+            v => std::slice::from_ref(v),
+        };
+        let mut out = Vec::new();
+        for item in items {
+            let v = match item {
+                Value::String(s) => s.clone(),
+                Value::Integer(n) => n.to_string(),
+                Value::Float(n) => n.to_string(),
+                Value::Boolean(on) if !arg.get_action().takes_values() => {
+                    if *on {
+                        out.push(format!("--{long}"));
+                    }
+                    continue;
+                }
+                Value::Boolean(on) => on.to_string(),
+                _ => bail!("`{key}` must be a string, number, boolean or array of them"),
+            };
+            out.push(format!("--{long}={v}"));
+        }
+        settings.push(out);
+    }
+
+    drop_unmet_requires(&mut settings, args);
+    Ok(settings.concat())
+}
+
+/// Remove settings that only fail because an option they require is missing
+/// (e.g. `watch` once `--output` has displaced `output-dir`). clap does not expose
+/// `requires`, so each setting is tried with and without; repeat until stable,
+/// since dropping one setting can leave another unmet.
+fn drop_unmet_requires(settings: &mut Vec<Vec<String>>, args: &[OsString]) {
+    let missing = |settings: &[Vec<String>]| {
+        let mut merged = vec![args[0].clone()];
+        merged.extend(settings.concat().into_iter().map(OsString::from));
+        merged.extend(args.iter().skip(1).cloned());
+        matches!(
+            Cli::command().try_get_matches_from(merged),
+            Err(e) if e.kind() == ErrorKind::MissingRequiredArgument
+        )
+    };
+
+    while missing(settings) {
+        let Some(i) = (0..settings.len()).find(|&i| {
+            let mut rest = settings.clone();
+            rest.remove(i);
+            !missing(&rest)
+        }) else {
+            // The command line itself is incomplete; let clap report it.
+            return;
+        };
+        settings.remove(i);
+    }
+}
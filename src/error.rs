@@ -0,0 +1,54 @@
+use crate::xml::XmlSyntaxError;
+use std::io;
+
+/// Why a library call failed.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// Reading the input failed.
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+
+    /// The input is not UTF-8 text.
+    #[error("encoding error: {0}")]
+    Encoding(String),
+
+    /// The input is not well-formed XML.
+    #[error(transparent)]
+    Syntax(#[from] XmlSyntaxError),
+
+    /// The document's root element is not a metadata type the structured parser
+    /// models (see [`parse_metadata`](crate::parse_metadata)).
+    #[error("unsupported metadata type: {0}")]
+    UnsupportedType(String),
+
+    /// An option value was rejected, such as a malformed path pattern.
+    #[error("invalid option: {0}")]
+    InvalidOption(String),
+
+    /// Anything else, such as a failure while writing an output format.
+    #[error("{0}")]
+    Other(String),
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+impl Error {
+    /// Sort an internal error into the matching variant by its root cause.
+    pub(crate) fn from_anyhow(err: anyhow::Error) -> Self {
+        if let Some(syntax) = err.chain().find_map(|e| e.downcast_ref::<XmlSyntaxError>()) {
+            return Error::Syntax(syntax.clone());
+        }
+        if let Some(utf8) = err
+            .chain()
+            .find_map(|e| e.downcast_ref::<std::str::Utf8Error>())
+        {
+            return Error::Encoding(utf8.to_string());
+        }
+        match err.downcast::<io::Error>() {
+            Ok(io) if io.kind() == io::ErrorKind::InvalidData => Error::Encoding(io.to_string()),
+            Ok(io) => Error::Io(io),
+            Err(err) => Error::Other(format!("{err:#}")),
+        }
+    }
+}
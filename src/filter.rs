@@ -0,0 +1,267 @@
+//! Narrow flattened entries down to the part of a file that matters
+//! (`--select`, `--drop`, `--where`).
+//!
+//! Patterns are written like flattened paths, slash or dot style:
+//! - `name` or `name[*]` matches that element at any index, `name[3]` only index 3
+//! - `*` matches any one element, `**` any number of segments
+//! - `#text`, `#cdata`, `@attr` and `@*` match leaves
+//!
+//! A pattern matches an entry when it matches the start of the entry's path, so
+//! `/Profile/fieldPermissions` selects every entry below each `fieldPermissions`.
+
+use crate::xml::FlatEntry;
+use crate::xml::tree::{path_segments, split_outside_braces};
+use anyhow::{Context, Result, bail};
+use regex::Regex;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PatSeg {
+    /// `name`, `name[*]` (`idx: None`) or `name[3]`.
+    Element {
+        name: String,
+        idx: Option<usize>,
+    },
+    AnyElement,
+    AnyDepth,
+    AnyAttr,
+    /// `#text`, `#cdata` or `@attr`, compared verbatim.
+    Leaf(String),
+}
+
+/// A compiled `--select`/`--drop` pattern.
+#[derive(Debug, Clone)]
+pub struct PathPattern {
+    segs: Vec<PatSeg>,
+}
+
+impl PathPattern {
+    pub fn parse(pattern: &str) -> Result<Self> {
+        let mut segs = Vec::new();
+        // Patterns may omit the leading `/`; the separator decides the style.
+        let body = pattern.strip_prefix('/').unwrap_or(pattern);
+        let sep = if split_outside_braces(pattern, '/').len() > 1 {
+            '/'
+        } else {
+            '.'
+        };
+        for raw in split_outside_braces(body, sep) {
+            let seg = match raw {
+                "" => bail!("Empty segment in pattern `{pattern}`"),
+                "**" => PatSeg::AnyDepth,
+                "*" => PatSeg::AnyElement,
+                "@*" => PatSeg::AnyAttr,
+                _ if raw.starts_with('#') || raw.starts_with('@') => PatSeg::Leaf(raw.to_string()),
+                _ => match raw.rsplit_once('[') {
+                    None => PatSeg::Element {
+                        name: raw.to_string(),
+                        idx: None,
+                    },
+                    Some((name, rest)) => {
+                        let idx = rest
+                            .strip_suffix(']')
+                            .with_context(|| format!("Unclosed `[` in pattern `{pattern}`"))?;
+                        PatSeg::Element {
+                            name: name.to_string(),
+                            idx: match idx {
+                                "*" => None,
+                                _ => Some(idx.parse().with_context(|| {
+                                    format!("Bad index `{idx}` in pattern `{pattern}`")
+                                })?),
+                            },
+                        }
+                    }
+                },
+            };
+            segs.push(seg);
+        }
+        Ok(Self { segs })
+    }
+
+    /// A bare element name (`errorConditionFormula`) matches that element at any
+    /// depth; anything else is parsed as a pattern.
+    pub fn element_or_pattern(s: &str) -> Result<Self> {
+        if s.contains(['/', '[', '*', '#', '@', '.']) {
+            Self::parse(s)
+        } else {
+            Self::parse(&format!("**/{s}"))
+        }
+    }
+
+    pub fn matches(&self, path: &str) -> bool {
+        let segs = path_segments(path);
+        match_prefix(&self.segs, &segs)
+    }
+}
+
+/// True if `pat` matches a prefix of `path`.
+fn match_prefix(pat: &[PatSeg], path: &[&str]) -> bool {
+    let Some((first, rest)) = pat.split_first() else {
+        return true;
+    };
+    if *first == PatSeg::AnyDepth {
+        return (0..=path.len()).any(|skip| match_prefix(rest, &path[skip..]));
+    }
+    let Some((seg, path_rest)) = path.split_first() else {
+        return false;
+    };
+    seg_matches(first, seg) && match_prefix(rest, path_rest)
+}
+
+fn seg_matches(pat: &PatSeg, seg: &str) -> bool {
+    let is_leaf = seg.starts_with('#') || seg.starts_with('@');
+    match pat {
+        PatSeg::AnyDepth => true,
+        PatSeg::AnyElement => !is_leaf,
+        PatSeg::AnyAttr => seg.starts_with('@'),
+        PatSeg::Leaf(leaf) => seg == leaf,
+        PatSeg::Element { name, idx } => {
+            let (seg_name, seg_idx) = split_index(seg);
+            !is_leaf && seg_name == name && idx.is_none_or(|i| seg_idx == Some(i))
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Test {
+    Regex(Regex),
+    Eq(String),
+    Ne(String),
+}
+
+/// A compiled `--where` predicate: `rel~regex`, `rel=value` or `rel!=value`.
+///
+/// `rel` names a child (`field`), a nested child (`layoutItems/field`) or an
+/// attribute (`@name`). The record it tests is the element holding `rel`; records
+/// of that shape without a matching child are dropped whole.
+#[derive(Debug, Clone)]
+pub struct Predicate {
+    rel: Vec<String>,
+    test: Test,
+}
+
+impl Predicate {
+    pub fn parse(expr: &str) -> Result<Self> {
+        let Some(op_at) = expr.find(['~', '=', '!']) else {
+            bail!("Expected `child~regex`, `child=value` or `child!=value`, got `{expr}`");
+        };
+        let (rel, rest) = expr.split_at(op_at);
+        let test = if let Some(re) = rest.strip_prefix('~') {
+            Test::Regex(Regex::new(re).with_context(|| format!("Bad regex in `{expr}`"))?)
+        } else if let Some(v) = rest.strip_prefix("!=") {
+            Test::Ne(v.to_string())
+        } else if let Some(v) = rest.strip_prefix('=') {
+            Test::Eq(v.to_string())
+        } else {
+            bail!("Expected `~`, `=` or `!=` after `{rel}` in `{expr}`");
+        };
+
+        let rel: Vec<String> = rel
+            .trim()
+            .split(['/', '.'])
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect();
+        if rel.is_empty() {
+            bail!("Missing child name in `{expr}`");
+        }
+        Ok(Self { rel, test })
+    }
+
+    fn accepts(&self, value: &str) -> bool {
+        match &self.test {
+            Test::Regex(re) => re.is_match(value),
+            Test::Eq(v) => value == v,
+            Test::Ne(v) => value != v,
+        }
+    }
+
+    /// Number of leading path segments naming the record, when `segs` is a value
+    /// of `rel` (element text or attribute).
+    fn record_len(&self, segs: &[&str]) -> Option<usize> {
+        let (leaf, elems) = segs.split_last()?;
+        let (names, attr) = match self.rel.split_last() {
+            Some((a, names)) if a.starts_with('@') => (names, Some(a.as_str())),
+            _ => (&self.rel[..], None),
+        };
+        let elems = match attr {
+            Some(a) if leaf == &a => elems,
+            Some(_) => return None,
+            None if *leaf == "#text" || *leaf == "#cdata" => elems,
+            None => return None,
+        };
+        let start = elems.len().checked_sub(names.len())?;
+        let tail = &elems[start..];
+        tail.iter()
+            .zip(names)
+            .all(|(seg, name)| split_index(seg).0 == name)
+            .then_some(start)
+    }
+}
+
+/// All filters from the command line, applied in the order where, select, drop.
+#[derive(Debug, Clone)]
+pub struct EntryFilter {
+    select: Vec<PathPattern>,
+    drop: Vec<PathPattern>,
+    predicates: Vec<Predicate>,
+}
+
+impl EntryFilter {
+    pub fn new(select: &[String], drop: &[String], predicates: &[String]) -> Result<Self> {
+        Ok(Self {
+            select: select
+                .iter()
+                .map(|p| PathPattern::parse(p))
+                .collect::<Result<_>>()?,
+            drop: drop
+                .iter()
+                .map(|p| PathPattern::parse(p))
+                .collect::<Result<_>>()?,
+            predicates: predicates
+                .iter()
+                .map(|p| Predicate::parse(p))
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    pub fn apply(&self, entries: &mut Vec<FlatEntry>) {
+        for pred in &self.predicates {
+            filter_records(entries, pred);
+        }
+        if !self.select.is_empty() {
+            entries.retain(|e| self.select.iter().any(|p| p.matches(&e.p)));
+        }
+        if !self.drop.is_empty() {
+            entries.retain(|e| !self.drop.iter().any(|p| p.matches(&e.p)));
+        }
+    }
+}
+
+/// Drop every record that `pred` applies to but no child of which satisfies it.
+fn filter_records(entries: &mut Vec<FlatEntry>, pred: &Predicate) {
+    // Record path -> whether any of its `rel` values passed.
+    let mut records: HashMap<String, bool> = HashMap::new();
+    for e in entries.iter() {
+        let segs = path_segments(&e.p);
+        if let Some(len) = pred.record_len(&segs) {
+            let pass = records.entry(segs[..len].join("/")).or_insert(false);
+            *pass |= pred.accepts(&e.v);
+        }
+    }
+    if records.values().all(|pass| *pass) {
+        return;
+    }
+
+    entries.retain(|e| {
+        let segs = path_segments(&e.p);
+        (1..=segs.len()).all(|len| records.get(&segs[..len].join("/")) != Some(&false))
+    });
+}
+
+fn split_index(seg: &str) -> (&str, Option<usize>) {
+    match seg.rsplit_once('[') {
+        Some((name, rest)) => (name, rest.strip_suffix(']').and_then(|i| i.parse().ok())),
+        None => (seg, None),
+    }
+}
@@ -1,97 +1,181 @@
-mod cli;
+//! Flatten Salesforce metadata XML (or any XML) into `(path, kind, value)` entries
+//! and render them in LLM-friendly formats.
+//!
+//! The same pipeline backs the `sfmeta-reader` binary. To embed only the library,
+//! turn off the default `cli` feature, which drops clap and the dependencies only
+//! the command line needs:
+//!
+//! ```toml
+//! sfmeta-reader = { version = "1", default-features = false }
+//! ```
+//!
+//! Two smaller features can be turned back on alone: `tokens` for the
+//! cl100k/o200k tokenizers (otherwise only [`Tokenizer::Approx`]) and `csv` for
+//! `render_csv`.
+//!
+//! ```
+//! use sfmeta_reader::{FlattenOptions, flatten_str, render_toon};
+//!
+//! let xml = r#"<CustomObject><fields><fullName>Rev__c</fullName></fields></CustomObject>"#;
+//! let opts = FlattenOptions::builder().max_text_len(0).build()?;
+//! let entries = flatten_str(xml, &opts)?;
+//! assert_eq!(
+//!     render_toon(&entries),
+//!     "/CustomObject[0]/fields[0]/fullName[0]/#text = Rev__c\n"
+//! );
+//! # Ok::<(), sfmeta_reader::Error>(())
+//! ```
+//!
+//! Every fallible call returns [`Error`], which tells I/O, encoding, XML syntax
+//! and unsupported-type failures apart.
+
+// Without the command line, some helpers shared with it go unused.
+#![cfg_attr(not(feature = "cli"), allow(dead_code, unused_imports))]
+
+mod budget;
+mod error;
+mod filter;
 pub mod parser;
 mod render;
 mod util;
 mod xml;
 
-use anyhow::{Context, Result};
-use clap::Parser;
-use std::path::{Path, PathBuf};
+#[cfg(feature = "cli")]
+mod app;
+#[cfg(feature = "cli")]
+mod cache;
+#[cfg(feature = "cli")]
+mod chunk;
+#[cfg(feature = "cli")]
+mod cli;
+#[cfg(feature = "cli")]
+mod commands;
+#[cfg(feature = "cli")]
+mod config;
+#[cfg(feature = "cli")]
+mod mirror;
+#[cfg(feature = "cli")]
+mod patch;
+#[cfg(feature = "cli")]
+mod query;
+#[cfg(feature = "cli")]
+mod watch;
+
+#[cfg(feature = "cli")]
+pub use app::run;
+pub use error::{Error, Result};
+#[cfg(feature = "csv")]
+pub use render::CsvTable;
+pub use render::{JsonMode, NdjsonMode, YamlMode};
+pub use util::Tokenizer;
+pub use xml::options::FlattenOptionsBuilder;
+pub use xml::{FlatEntry, FlattenOptions, NsMode, PathStyle, ValueKind, XmlSyntaxError};
+
+use parser::SalesforceMetadata;
+use quick_xml::Reader;
+use quick_xml::events::Event;
+use std::io::Read;
+use std::path::Path;
+
+/// Flatten an XML document into entries, in document order.
+pub fn flatten_str(xml: &str, opts: &FlattenOptions) -> Result<Vec<FlatEntry>> {
+    xml::flatten_xml_str(xml, opts).map_err(Error::from_anyhow)
+}
 
-use cli::{Cli, Format};
-use render::{render_compact, render_json, render_toon};
-use util::{estimate_tokens_approx, walk_inputs};
-use xml::{FlattenOptions, flatten_xml_file};
+/// Read a whole UTF-8 document from `reader` and flatten it.
+pub fn flatten_reader(mut reader: impl Read, opts: &FlattenOptions) -> Result<Vec<FlatEntry>> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    let xml = std::str::from_utf8(&bytes).map_err(|err| Error::Encoding(err.to_string()))?;
+    flatten_str(xml.trim_start_matches('\u{feff}'), opts)
+}
 
-pub fn run() -> Result<()> {
-    let cli = Cli::parse();
+/// Flatten a file; syntax errors name it in [`XmlSyntaxError::file`].
+pub fn flatten_file(path: impl AsRef<Path>, opts: &FlattenOptions) -> Result<Vec<FlatEntry>> {
+    xml::flatten_xml_file(path.as_ref(), opts).map_err(Error::from_anyhow)
+}
 
-    let inputs = walk_inputs(&cli.input, cli.recursive, &cli.glob)
-        .with_context(|| format!("Failed to enumerate input path(s): {}", cli.input.display()))?;
+/// `path = value` lines.
+pub fn render_toon(entries: &[FlatEntry]) -> String {
+    render::render_toon::render(entries)
+}
 
-    if inputs.is_empty() {
-        anyhow::bail!(
-            "No input files matched (input={}, glob={})",
-            cli.input.display(),
-            cli.glob
-        );
-    }
+/// One `path=value|path=value|...` line.
+pub fn render_compact(entries: &[FlatEntry]) -> String {
+    render::render_compact::render(entries)
+}
 
-    let mut outputs: Vec<(PathBuf, String)> = Vec::with_capacity(inputs.len());
+/// A JSON list of entries, a path-to-value map, or nested objects.
+pub fn render_json(entries: &[FlatEntry], mode: JsonMode, minify: bool) -> Result<String> {
+    render::render_json::render(entries, mode, minify, None).map_err(Error::from_anyhow)
+}
 
-    for path in inputs {
-        let out = process_one(&path, &cli)?;
-        outputs.push((path, out));
-    }
+/// A YAML mapping of paths to values, or nested mappings.
+pub fn render_yaml(entries: &[FlatEntry], mode: YamlMode) -> Result<String> {
+    render::render_yaml::render(entries, mode).map_err(Error::from_anyhow)
+}
 
-    let final_text = if outputs.len() == 1 && !cli.always_wrap {
-        outputs.into_iter().next().unwrap().1
-    } else {
-        // Multi-doc wrapper (keeps format valid/usable).
-        match cli.format {
-            Format::Json => render_json::wrap_many(outputs, cli.json_mode, cli.minify)?,
-            Format::Toon => render_toon::wrap_many(outputs),
-            Format::Compact => render_compact::wrap_many(outputs),
-        }
-    };
+/// JSON Lines naming `file` on every line.
+pub fn render_ndjson(
+    file: impl AsRef<Path>,
+    entries: &[FlatEntry],
+    mode: NdjsonMode,
+) -> Result<String> {
+    render::render_ndjson::render(file.as_ref(), entries, mode, None).map_err(Error::from_anyhow)
+}
 
-    if cli.show_tokens {
-        let tokens = estimate_tokens_approx(&final_text);
-        eprintln!("Approx output tokens: {}", tokens);
-    }
+/// A Markdown report headed by `file`'s name.
+pub fn render_markdown(file: impl AsRef<Path>, entries: &[FlatEntry]) -> Result<String> {
+    render::render_markdown::render(file.as_ref(), entries).map_err(Error::from_anyhow)
+}
 
-    match &cli.output {
-        Some(out_path) => {
-            std::fs::write(out_path, final_text)
-                .with_context(|| format!("Failed to write output file: {}", out_path.display()))?;
-        }
-        None => {
-            print!("{final_text}");
-        }
-    }
+/// Permission or field inventory rows, with a header line.
+#[cfg(feature = "csv")]
+pub fn render_csv(
+    file: impl AsRef<Path>,
+    entries: &[FlatEntry],
+    table: CsvTable,
+) -> Result<String> {
+    render::render_csv::render(file.as_ref(), entries, table).map_err(Error::from_anyhow)
+}
 
-    Ok(())
+/// Rebuild Metadata API XML from entries (as `to-xml` does).
+pub fn to_xml(entries: &[FlatEntry]) -> Result<String> {
+    xml::build_document(entries)
+        .and_then(|doc| xml::write_document(&doc))
+        .map_err(Error::from_anyhow)
 }
 
-fn process_one(path: &Path, cli: &Cli) -> Result<String> {
-    if !path.exists() {
-        anyhow::bail!("Input file does not exist: {}", path.display());
+/// Parse into the typed [`SalesforceMetadata`] model. Fails with
+/// [`Error::UnsupportedType`] when the root element is not one of
+/// [`parser::SUPPORTED_TYPES`]; use [`flatten_str`] for those.
+pub fn parse_metadata(xml: &str, include_descriptions: bool) -> Result<SalesforceMetadata> {
+    let root = root_element(xml)?;
+    if !parser::SUPPORTED_TYPES.contains(&root.as_str()) {
+        return Err(Error::UnsupportedType(root));
     }
+    parser::parse_salesforce_xml(xml, include_descriptions).map_err(Error::from_anyhow)
+}
 
-    // Flatten any XML into generic (path, kind, value) entries.
-    // If parsing fails, you can decide to hard-fail or skip; we hard-fail by default.
-    let opts = FlattenOptions {
-        include_attributes: cli.include_attributes,
-        include_text: true,
-        include_cdata: true,
-        strip_namespace_prefix: !cli.keep_ns_prefix,
-        max_text_len: cli.max_text_len,
-        path_style: cli.path_style,
-    };
-
-    let flat = flatten_xml_file(path, &opts)
-        .with_context(|| format!("XML flatten failed: {}", path.display()))?;
-
-    let rendered = match cli.format {
-        Format::Json => render_json::render(&flat, cli.json_mode, cli.minify)?,
-        Format::Toon => render_toon::render(&flat),
-        Format::Compact => render_compact::render(&flat),
-    };
-
-    if cli.show_tokens_per_file {
-        let tokens = estimate_tokens_approx(&rendered);
-        eprintln!("{} -> approx tokens: {}", path.display(), tokens);
+/// Local name of the document's root element.
+fn root_element(xml: &str) -> Result<String> {
+    let mut reader = Reader::from_str(xml);
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e) | Event::Empty(e)) => {
+                return Ok(String::from_utf8_lossy(e.local_name().as_ref()).into_owned());
+            }
+            Ok(Event::Eof) => {
+                return Err(
+                    XmlSyntaxError::new(xml, reader.error_position(), "no root element").into(),
+                );
+            }
+            Ok(_) => {}
+            Err(err) => {
+                return Err(
+                    XmlSyntaxError::new(xml, reader.error_position(), err.to_string()).into(),
+                );
+            }
+        }
     }
-
-    Ok(rendered)
 }
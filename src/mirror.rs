@@ -0,0 +1,37 @@
+//! One rendered file per input under `--output-dir`, laid out like the source tree:
+//! `objects/Account/Account.object-meta.xml` becomes `objects/Account/Account.object.toon`.
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+/// Where `file` (found under the input `root`) is mirrored to in `dir`.
+pub fn mirror_path(root: &Path, file: &Path, dir: &Path, ext: &str) -> PathBuf {
+    let rel = match file.strip_prefix(root) {
+        Ok(rel) if !rel.as_os_str().is_empty() => rel,
+        // The input was a single file.
+        _ => Path::new(file.file_name().unwrap_or(file.as_os_str())),
+    };
+    let name = rel.file_name().unwrap_or_default().to_string_lossy();
+    let stem = name
+        .strip_suffix("-meta.xml")
+        .or_else(|| name.strip_suffix(".xml"))
+        .unwrap_or(&name);
+    dir.join(rel).with_file_name(format!("{stem}.{ext}"))
+}
+
+/// The directory in `dir` that mirrors `path` (which need not exist any more).
+pub fn mirror_dir(root: &Path, path: &Path, dir: &Path) -> Option<PathBuf> {
+    path.strip_prefix(root)
+        .ok()
+        .filter(|rel| !rel.as_os_str().is_empty())
+        .map(|rel| dir.join(rel))
+}
+
+pub fn write(out: &Path, body: &str) -> Result<()> {
+    if let Some(parent) = out.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create output directory: {}", parent.display()))?;
+    }
+    std::fs::write(out, body)
+        .with_context(|| format!("Failed to write output file: {}", out.display()))
+}
@@ -8,6 +8,16 @@ pub use format::{to_compact_format, to_toon_format};
 pub use parse::parse_salesforce_xml;
 pub use types::*;
 
+/// Root elements [`parse_salesforce_xml`] has a model for. Other types parse, but
+/// only their common fields (`fullName`, `label`, ...) are filled in.
+pub const SUPPORTED_TYPES: &[&str] = &[
+    "CustomObject",
+    "CustomField",
+    "Profile",
+    "PermissionSet",
+    "Flow",
+];
+
 /// Root metadata container aggregating all parsed Salesforce metadata types.
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct SalesforceMetadata {
@@ -4,6 +4,7 @@ use quick_xml::events::Event;
 
 use super::SalesforceMetadata;
 use super::types::*;
+use crate::xml::XmlSyntaxError;
 
 /// Enhanced parser supporting 300+ Salesforce metadata types.
 ///
@@ -42,8 +43,8 @@ pub fn parse_salesforce_xml(xml: &str, include_descriptions: bool) -> Result<Sal
                 let tag_name = String::from_utf8_lossy(binding.as_ref()).to_string();
 
                 match tag_name.as_str() {
-                    // Object metadata
-                    "fields" => {
+                    // Object metadata; a source-format field file is a single field.
+                    "fields" | "CustomField" => {
                         in_field = true;
                         current_field = Some(Field {
                             name: String::new(),
@@ -148,10 +149,8 @@ pub fn parse_salesforce_xml(xml: &str, include_descriptions: bool) -> Result<Sal
                             "required" => field.required = text.eq_ignore_ascii_case("true"),
                             "unique" => field.unique = text.eq_ignore_ascii_case("true"),
                             "length" => field.length = text.parse().ok(),
-                            "description" => {
-                                if include_descriptions {
-                                    field.description = Some(text);
-                                }
+                            "description" if include_descriptions => {
+                                field.description = Some(text);
                             }
                             _ => {}
                         }
@@ -208,10 +207,8 @@ pub fn parse_salesforce_xml(xml: &str, include_descriptions: bool) -> Result<Sal
                         "fullName" => metadata.full_name = Some(text),
                         "type" => metadata.object_type = Some(text),
                         "label" => metadata.label = Some(text),
-                        "description" => {
-                            if include_descriptions {
-                                metadata.description = Some(text);
-                            }
+                        "description" if include_descriptions => {
+                            metadata.description = Some(text);
                         }
                         _ => {}
                     }
@@ -222,77 +219,65 @@ pub fn parse_salesforce_xml(xml: &str, include_descriptions: bool) -> Result<Sal
                 let tag_name = String::from_utf8_lossy(binding.as_ref());
 
                 match tag_name.as_ref() {
-                    "fields" => {
-                        if in_field {
-                            if let Some(field) = current_field.take()
-                                && !field.name.is_empty()
-                            {
-                                metadata.fields.push(field);
-                            }
-                            in_field = false;
+                    "fields" | "CustomField" if in_field => {
+                        if let Some(field) = current_field.take()
+                            && !field.name.is_empty()
+                        {
+                            metadata.fields.push(field);
                         }
+                        in_field = false;
                     }
-                    "validationRules" => {
-                        if in_validation {
-                            if let Some(validation) = current_validation.take()
-                                && !validation.name.is_empty()
-                            {
-                                metadata.validation_rules.push(validation);
-                            }
-                            in_validation = false;
+                    "validationRules" if in_validation => {
+                        if let Some(validation) = current_validation.take()
+                            && !validation.name.is_empty()
+                        {
+                            metadata.validation_rules.push(validation);
                         }
+                        in_validation = false;
                     }
-                    "workflow" | "workflowRules" => {
-                        if in_workflow {
-                            if let Some(workflow) = current_workflow.take()
-                                && !workflow.name.is_empty()
-                            {
-                                metadata.workflows.push(workflow);
-                            }
-                            in_workflow = false;
+                    "workflow" | "workflowRules" if in_workflow => {
+                        if let Some(workflow) = current_workflow.take()
+                            && !workflow.name.is_empty()
+                        {
+                            metadata.workflows.push(workflow);
                         }
+                        in_workflow = false;
                     }
-                    "recordTypes" => {
-                        if in_record_type {
-                            if let Some(rt) = current_record_type.take()
-                                && !rt.name.is_empty()
-                            {
-                                metadata.record_types.push(rt);
-                            }
-                            in_record_type = false;
+                    "recordTypes" if in_record_type => {
+                        if let Some(rt) = current_record_type.take()
+                            && !rt.name.is_empty()
+                        {
+                            metadata.record_types.push(rt);
                         }
+                        in_record_type = false;
                     }
-                    "objectPermissions" => {
-                        if in_obj_permission {
-                            if let Some(perm) = current_obj_perm.take()
-                                && !perm.object.is_empty()
+                    "objectPermissions" if in_obj_permission => {
+                        if let Some(perm) = current_obj_perm.take()
+                            && !perm.object.is_empty()
+                        {
+                            if in_profile && let Some(ref mut profile) = metadata.profile {
+                                profile.object_permissions.push(perm);
+                            } else if in_permission_set
+                                && let Some(ref mut ps) = metadata.permission_set
                             {
-                                if in_profile && let Some(ref mut profile) = metadata.profile {
-                                    profile.object_permissions.push(perm);
-                                } else if in_permission_set
-                                    && let Some(ref mut ps) = metadata.permission_set
-                                {
-                                    ps.object_permissions.push(perm);
-                                }
+                                ps.object_permissions.push(perm);
                             }
-                            in_obj_permission = false;
                         }
+                        in_obj_permission = false;
                     }
-                    "fieldPermissions" => {
-                        if in_field_permission {
-                            if let Some(perm) = current_field_perm.take()
-                                && !perm.field.is_empty()
+                    "fieldPermissions" if in_field_permission => {
+                        if let Some(perm) = current_field_perm.take()
+                            && !perm.field.is_empty()
+                        {
+                            if in_profile && let Some(ref mut profile) = metadata.profile {
+                                profile.field_permissions.push(perm);
+                            } else if in_permission_set
+                                && let Some(ref mut ps) = metadata.permission_set
                             {
-                                if in_profile && let Some(ref mut profile) = metadata.profile {
-                                    profile.field_permissions.push(perm);
-                                } else if in_permission_set
-                                    && let Some(ref mut ps) = metadata.permission_set
-                                {
-                                    ps.field_permissions.push(perm);
-                                }
+                                ps.field_permissions.push(perm);
                             }
-                            in_field_permission = false;
                         }
+                        in_field_permission = false;
                     }
                     "Profile" => in_profile = false,
                     "PermissionSet" => in_permission_set = false,
@@ -301,11 +286,9 @@ pub fn parse_salesforce_xml(xml: &str, include_descriptions: bool) -> Result<Sal
             }
             Ok(Event::Eof) => break,
             Err(e) => {
-                return Err(anyhow::anyhow!(
-                    "XML parsing error at position {}: {}",
-                    reader.error_position(),
-                    e
-                ));
+                return Err(
+                    XmlSyntaxError::new(xml, reader.error_position(), e.to_string()).into(),
+                );
             }
             _ => {}
         }
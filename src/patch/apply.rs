@@ -0,0 +1,344 @@
+use crate::patch::index::{ElementSpan, index_document};
+use crate::patch::ops::{OpKind, PatchOp};
+use crate::xml::tree::Segment;
+use anyhow::{Result, bail};
+use quick_xml::escape::escape;
+
+/// Indent used for new children when the document offers nothing to copy.
+const DEFAULT_INDENT: &str = "    ";
+
+/// Apply one operation to `xml`, touching only the bytes the operation addresses.
+pub fn apply_op(xml: &str, op: &PatchOp) -> Result<String> {
+    let root = index_document(xml)?;
+
+    let (leaf, elems) = match op.segments.split_last() {
+        Some((Segment::Element { .. }, _)) => (None, op.segments.as_slice()),
+        Some((leaf, elems)) => (Some(leaf), elems),
+        None => bail!("Empty patch path"),
+    };
+    let names: Vec<(&str, usize)> = elems
+        .iter()
+        .map(|s| match s {
+            Segment::Element { name, idx } => Ok((name.as_str(), *idx)),
+            _ => bail!("Leaf segment in the middle of path: {}", op.path),
+        })
+        .collect::<Result<_>>()?;
+
+    match op.kind {
+        OpKind::Delete => {
+            let (found, rest) = resolve(&root, &names);
+            if !rest.is_empty() {
+                bail!("Nothing to delete at {}", op.path);
+            }
+            match leaf {
+                None => Ok(remove_element(xml, found)),
+                Some(Segment::Text | Segment::CData) => set_text(xml, found, leaf, ""),
+                Some(Segment::Attr(key)) => remove_attr(xml, found, key, &op.path),
+                Some(Segment::Element { .. }) => unreachable!("element leaves are split off above"),
+                Some(Segment::Comment | Segment::Pi | Segment::DocType | Segment::Namespace(_)) => {
+                    unreachable!("rejected by parse_op")
+                }
+            }
+        }
+        OpKind::Set => {
+            let value = op.value.as_deref().unwrap_or_default();
+            let (found, rest) = resolve(&root, &names);
+            if rest.is_empty() {
+                return match leaf {
+                    Some(Segment::Attr(key)) => set_attr(xml, found, key, value),
+                    _ => set_text(xml, found, leaf, value),
+                };
+            }
+            // Create the missing tail under the deepest element that exists. Only the
+            // next free index may be created, so a mistyped index cannot append.
+            let (name, idx) = rest[0];
+            let count = found.named(name).count();
+            if idx != count {
+                bail!(
+                    "{} is out of range: <{}> has {count} <{name}> children, so only [{count}] can be added",
+                    op.path,
+                    found.raw_name
+                );
+            }
+            if let Some((name, idx)) = rest[1..].iter().find(|(_, idx)| *idx != 0) {
+                bail!(
+                    "{} is out of range: the new element can only hold {name}[0], not [{idx}]",
+                    op.path
+                );
+            }
+            let anchor = found.named(name).last().or(found.children.last());
+            let chain: Vec<&str> = rest.iter().map(|(n, _)| *n).collect();
+            add_child(xml, found, anchor, false, &chain, leaf, Some(value))
+        }
+        OpKind::Insert => {
+            let Some(((name, idx), parents)) = names.split_last() else {
+                bail!("Nothing to insert at {}", op.path);
+            };
+            if parents.is_empty() {
+                bail!("Cannot insert a second root element");
+            }
+            let (parent, rest) = resolve(&root, parents);
+            if !rest.is_empty() {
+                bail!("Parent of {} does not exist", op.path);
+            }
+            let siblings: Vec<&ElementSpan> = parent.named(name).collect();
+            let (anchor, before) = match siblings.iter().find(|s| s.idx == *idx) {
+                Some(s) => (Some(*s), true),
+                None => (siblings.last().copied().or(parent.children.last()), false),
+            };
+            add_child(
+                xml,
+                parent,
+                anchor,
+                before,
+                &[name],
+                leaf,
+                op.value.as_deref(),
+            )
+        }
+    }
+}
+
+/// Walk `names` down from `root`; returns the deepest element found and the
+/// segments that did not resolve.
+fn resolve<'a, 'n>(
+    root: &'a ElementSpan,
+    names: &'n [(&'n str, usize)],
+) -> (&'a ElementSpan, &'n [(&'n str, usize)]) {
+    let mut node = root;
+    for (i, (name, idx)) in names.iter().enumerate() {
+        match node.child(name, *idx) {
+            Some(child) => node = child,
+            None => return (node, &names[i..]),
+        }
+    }
+    (node, &[])
+}
+
+fn splice(xml: &str, start: usize, end: usize, with: &str) -> String {
+    let mut s = String::with_capacity(xml.len() + with.len());
+    s.push_str(&xml[..start]);
+    s.push_str(with);
+    s.push_str(&xml[end..]);
+    s
+}
+
+/// Whitespace between the start of the line and `pos`, if `pos` starts its line.
+fn indent_at(xml: &str, pos: usize) -> &str {
+    let line_start = xml[..pos].rfind('\n').map_or(0, |i| i + 1);
+    let lead = &xml[line_start..pos];
+    if lead.chars().all(char::is_whitespace) {
+        lead
+    } else {
+        ""
+    }
+}
+
+/// `<name attr="...">` for an element written as `<name attr="..."/>`.
+fn opened_empty_tag(xml: &str, el: &ElementSpan) -> String {
+    let tag = &xml[el.start..el.end];
+    let tag = tag.trim_end_matches('>').trim_end_matches('/').trim_end();
+    format!("{tag}>")
+}
+
+fn leaf_markup(value: &str, leaf: Option<&Segment>) -> String {
+    match leaf {
+        // `]]>` would end the section early, so split it across two sections.
+        Some(Segment::CData) => format!("<![CDATA[{}]]>", value.replace("]]>", "]]]]><![CDATA[>")),
+        _ => escape(value).to_string(),
+    }
+}
+
+fn set_text(xml: &str, el: &ElementSpan, leaf: Option<&Segment>, value: &str) -> Result<String> {
+    if !el.children.is_empty() {
+        bail!(
+            "<{}> has child elements; address one of them instead",
+            el.raw_name
+        );
+    }
+    let content = leaf_markup(value, leaf);
+    Ok(match el.close_start {
+        Some(close) => splice(xml, el.open_end, close, &content),
+        None if content.is_empty() => xml.to_string(),
+        None => {
+            let open = opened_empty_tag(xml, el);
+            let replaced = format!("{open}{content}</{}>", el.raw_name);
+            splice(xml, el.start, el.end, &replaced)
+        }
+    })
+}
+
+/// Position of one attribute inside a start tag: the span to drop on removal
+/// (including the whitespace before it) and the span of its value.
+struct AttrSpan {
+    start: usize,
+    value_start: usize,
+    value_end: usize,
+    end: usize,
+}
+
+fn find_attr(xml: &str, el: &ElementSpan, key: &str) -> Option<AttrSpan> {
+    let tag = &xml[el.start..el.open_end];
+    let bytes = tag.as_bytes();
+    let mut i = 1 + el.raw_name.len();
+
+    loop {
+        let ws_start = i;
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        if i >= bytes.len() || bytes[i] == b'>' || bytes[i] == b'/' {
+            return None;
+        }
+        let key_start = i;
+        while i < bytes.len() && bytes[i] != b'=' && !bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        let found = &tag[key_start..i];
+        while i < bytes.len() && (bytes[i] == b'=' || bytes[i].is_ascii_whitespace()) {
+            i += 1;
+        }
+        let quote = *bytes.get(i)?;
+        let value_start = i + 1;
+        let value_end = value_start + tag[value_start..].find(quote as char)?;
+        i = value_end + 1;
+
+        if found == key {
+            return Some(AttrSpan {
+                start: el.start + ws_start,
+                value_start: el.start + value_start,
+                value_end: el.start + value_end,
+                end: el.start + i,
+            });
+        }
+    }
+}
+
+fn escape_attr(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn set_attr(xml: &str, el: &ElementSpan, key: &str, value: &str) -> Result<String> {
+    let value = escape_attr(value);
+    if let Some(a) = find_attr(xml, el, key) {
+        return Ok(splice(xml, a.value_start, a.value_end, &value));
+    }
+    // Insert before `>` or `/>` of the start tag.
+    let tag = &xml[el.start..el.open_end];
+    let close_len = if tag.ends_with("/>") { 2 } else { 1 };
+    let tag_body_end = el.start + tag[..tag.len() - close_len].trim_end().len();
+    Ok(splice(
+        xml,
+        tag_body_end,
+        tag_body_end,
+        &format!(" {key}=\"{value}\""),
+    ))
+}
+
+fn remove_attr(xml: &str, el: &ElementSpan, key: &str, path: &str) -> Result<String> {
+    match find_attr(xml, el, key) {
+        Some(a) => Ok(splice(xml, a.start, a.end, "")),
+        None => bail!("Nothing to delete at {path}"),
+    }
+}
+
+/// Remove an element; when it sits alone on its line(s) the whole line goes too.
+fn remove_element(xml: &str, el: &ElementSpan) -> String {
+    let indent = indent_at(xml, el.start);
+    let rest = &xml[el.end..];
+    let line_end = rest.find('\n');
+    let trailing_blank = match line_end {
+        Some(i) => rest[..i].trim().is_empty(),
+        None => rest.trim().is_empty(),
+    };
+
+    if trailing_blank
+        && (el.start == indent.len() || xml[..el.start - indent.len()].ends_with('\n'))
+    {
+        let end = line_end.map_or(xml.len(), |i| el.end + i + 1);
+        splice(xml, el.start - indent.len(), end, "")
+    } else {
+        splice(xml, el.start, el.end, "")
+    }
+}
+
+/// Markup for a chain of new nested elements, innermost carrying the leaf value.
+fn chain_markup(
+    chain: &[&str],
+    leaf: Option<&Segment>,
+    value: Option<&str>,
+    indent: &str,
+    unit: &str,
+) -> String {
+    let (name, rest) = chain.split_first().expect("chain is never empty");
+    if !rest.is_empty() {
+        let inner_indent = format!("{indent}{unit}");
+        let inner = chain_markup(rest, leaf, value, &inner_indent, unit);
+        return format!("<{name}>\n{inner_indent}{inner}\n{indent}</{name}>");
+    }
+    match (leaf, value) {
+        (Some(Segment::Attr(key)), Some(v)) => format!("<{name} {key}=\"{}\"/>", escape_attr(v)),
+        (_, Some(v)) if !v.is_empty() || leaf.is_some() => {
+            format!("<{name}>{}</{name}>", leaf_markup(v, leaf))
+        }
+        _ => format!("<{name}/>"),
+    }
+}
+
+/// Add new element(s) under `parent`, before or after `anchor` (one of its
+/// children), copying the indentation of the surrounding markup.
+fn add_child(
+    xml: &str,
+    parent: &ElementSpan,
+    anchor: Option<&ElementSpan>,
+    before: bool,
+    chain: &[&str],
+    leaf: Option<&Segment>,
+    value: Option<&str>,
+) -> Result<String> {
+    let unit = indent_unit(xml);
+    let parent_indent = indent_at(xml, parent.start);
+    let child_indent = match parent.children.first() {
+        Some(c) => indent_at(xml, c.start).to_string(),
+        None => format!("{parent_indent}{unit}"),
+    };
+    let markup = chain_markup(chain, leaf, value, &child_indent, &unit);
+
+    Ok(match (anchor, before) {
+        (Some(a), true) => splice(xml, a.start, a.start, &format!("{markup}\n{child_indent}")),
+        (Some(a), false) => splice(xml, a.end, a.end, &format!("\n{child_indent}{markup}")),
+        (None, _) => {
+            let body = format!("\n{child_indent}{markup}\n{parent_indent}");
+            match parent.close_start {
+                Some(close) => {
+                    if !xml[parent.open_end..close].trim().is_empty() {
+                        bail!(
+                            "<{}> holds text; cannot add child elements",
+                            parent.raw_name
+                        );
+                    }
+                    splice(xml, parent.open_end, close, &body)
+                }
+                None => {
+                    let open = opened_empty_tag(xml, parent);
+                    let replaced = format!("{open}{body}</{}>", parent.raw_name);
+                    splice(xml, parent.start, parent.end, &replaced)
+                }
+            }
+        }
+    })
+}
+
+/// Indentation step of the document, taken from the first indented line.
+fn indent_unit(xml: &str) -> String {
+    xml.lines()
+        .skip(1)
+        .map(|l| &l[..l.len() - l.trim_start().len()])
+        .find(|lead| !lead.is_empty())
+        .unwrap_or(DEFAULT_INDENT)
+        .to_string()
+}
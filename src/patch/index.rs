@@ -0,0 +1,124 @@
+use crate::xml::XmlSyntaxError;
+use anyhow::Result;
+use quick_xml::Reader;
+use quick_xml::events::{BytesStart, Event};
+use std::collections::HashMap;
+
+/// Byte spans of one element in the source document.
+///
+/// `start..open_end` is the start tag (the whole tag for `<a/>`), `close_start..end`
+/// the end tag; `close_start` is `None` for empty elements.
+#[derive(Debug, Clone)]
+pub struct ElementSpan {
+    /// Local name, matched against flattened paths.
+    pub name: String,
+    /// Name as written (with any prefix), used when rewriting end tags.
+    pub raw_name: String,
+    pub idx: usize,
+    pub start: usize,
+    pub open_end: usize,
+    pub close_start: Option<usize>,
+    pub end: usize,
+    pub children: Vec<ElementSpan>,
+}
+
+impl ElementSpan {
+    /// The element children named `name`, in document order.
+    pub fn named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a ElementSpan> {
+        self.children.iter().filter(move |c| c.name == name)
+    }
+
+    pub fn child(&self, name: &str, idx: usize) -> Option<&ElementSpan> {
+        self.children
+            .iter()
+            .find(|c| c.name == name && c.idx == idx)
+    }
+}
+
+/// Index every element of `xml` by the same (name, sibling index) keys the
+/// flattener uses. The returned span is a virtual parent of the root element.
+pub fn index_document(xml: &str) -> Result<ElementSpan> {
+    let mut reader = Reader::from_str(xml);
+
+    let mut stack = vec![ElementSpan {
+        name: String::new(),
+        raw_name: String::new(),
+        idx: 0,
+        start: 0,
+        open_end: 0,
+        close_start: Some(xml.len()),
+        end: xml.len(),
+        children: Vec::new(),
+    }];
+    let mut counts: Vec<HashMap<String, usize>> = vec![HashMap::new()];
+
+    loop {
+        let start = reader.buffer_position() as usize;
+        match reader.read_event() {
+            Ok(Event::Start(e)) => {
+                let end = reader.buffer_position() as usize;
+                let span = new_span(&e, start, end, &mut counts);
+                stack.push(span);
+                counts.push(HashMap::new());
+            }
+            Ok(Event::Empty(e)) => {
+                let end = reader.buffer_position() as usize;
+                let span = new_span(&e, start, end, &mut counts);
+                stack
+                    .last_mut()
+                    .expect("virtual root is never popped")
+                    .children
+                    .push(span);
+            }
+            Ok(Event::End(_)) => {
+                let mut span = stack.pop().expect("reader checks end tag balance");
+                counts.pop();
+                span.close_start = Some(start);
+                span.end = reader.buffer_position() as usize;
+                stack
+                    .last_mut()
+                    .expect("virtual root is never popped")
+                    .children
+                    .push(span);
+            }
+            Ok(Event::Eof) => break,
+            Ok(_) => {}
+            Err(err) => {
+                return Err(
+                    XmlSyntaxError::new(xml, reader.error_position(), err.to_string()).into(),
+                );
+            }
+        }
+    }
+
+    Ok(stack.swap_remove(0))
+}
+
+fn new_span(
+    e: &BytesStart<'_>,
+    start: usize,
+    end: usize,
+    counts: &mut [HashMap<String, usize>],
+) -> ElementSpan {
+    let raw_name = String::from_utf8_lossy(e.name().as_ref()).to_string();
+    let name = match raw_name.rfind(':') {
+        Some(i) => raw_name[i + 1..].to_string(),
+        None => raw_name.clone(),
+    };
+
+    let level = counts.last_mut().expect("counts mirrors the element stack");
+    let idx = level.entry(name.clone()).or_insert(0);
+    let current = *idx;
+    *idx += 1;
+
+    ElementSpan {
+        name,
+        raw_name,
+        idx: current,
+        start,
+        open_end: end,
+        close_start: None,
+        end,
+        children: Vec::new(),
+    }
+}
@@ -0,0 +1,21 @@
+//! Apply edits expressed in flattened paths back to the original XML.
+//!
+//! Edits splice bytes into the source text, so formatting, comments and element
+//! order outside the addressed elements are left exactly as they were.
+
+mod apply;
+mod index;
+mod ops;
+
+pub use ops::{PatchOp, parse_ops};
+
+use anyhow::{Context, Result};
+
+/// Apply `ops` in order; each one sees the indexes produced by the previous ones.
+pub fn apply_ops(xml: &str, ops: &[PatchOp]) -> Result<String> {
+    let mut out = xml.to_string();
+    for op in ops {
+        out = apply::apply_op(&out, op).with_context(|| format!("Patch failed at {}", op.path))?;
+    }
+    Ok(out)
+}
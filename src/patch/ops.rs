@@ -0,0 +1,85 @@
+use crate::render::render_toon;
+use crate::xml::tree::{Segment, parse_path};
+use anyhow::{Result, bail};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpKind {
+    Set,
+    Insert,
+    Delete,
+}
+
+/// One edit addressed with a flattened path, e.g.
+/// `set /Profile[0]/fieldPermissions[2]/editable[0]/#text = true`.
+#[derive(Debug, Clone)]
+pub struct PatchOp {
+    pub kind: OpKind,
+    pub path: String,
+    pub segments: Vec<Segment>,
+    pub value: Option<String>,
+}
+
+/// Parse one operation per line. `set`, `insert` and `delete` prefixes are
+/// recognised; a bare `path = value` line is a `set`. Values use TOON escaping,
+/// and blank lines and `#` comments are skipped.
+pub fn parse_ops(text: &str) -> Result<Vec<PatchOp>> {
+    let mut ops = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim_start();
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match parse_op(line) {
+            Ok(op) => ops.push(op),
+            Err(err) => bail!("Patch line {}: {err}", n + 1),
+        }
+    }
+    Ok(ops)
+}
+
+pub fn parse_op(line: &str) -> Result<PatchOp> {
+    let (kind, rest) = if let Some(rest) = line.strip_prefix("set ") {
+        (OpKind::Set, rest)
+    } else if let Some(rest) = line.strip_prefix("insert ") {
+        (OpKind::Insert, rest)
+    } else if let Some(rest) = line.strip_prefix("delete ") {
+        (OpKind::Delete, rest)
+    } else {
+        (OpKind::Set, line)
+    };
+
+    let (path, value) = match rest.split_once(" = ") {
+        Some((p, v)) => (p.trim(), Some(render_toon::unescape_value(v))),
+        None => (rest.trim(), None),
+    };
+
+    match (kind, &value) {
+        (OpKind::Set, None) => bail!("`set` needs `path = value`: {line}"),
+        (OpKind::Delete, Some(_)) => bail!("`delete` takes a path only: {line}"),
+        _ => {}
+    }
+    if !path.starts_with('/') {
+        bail!("Patch paths must use the slash style (`/Root[0]/...`): {path}");
+    }
+
+    let segments = parse_path(path)?;
+    if !matches!(segments.first(), Some(Segment::Element { .. })) {
+        bail!("Patch path must start at the root element: {path}");
+    }
+    if matches!(
+        segments.last(),
+        Some(Segment::Comment | Segment::Pi | Segment::DocType)
+    ) {
+        bail!("Comments and processing instructions cannot be patched: {path}");
+    }
+    if matches!(segments.last(), Some(Segment::Namespace(_))) {
+        bail!("Namespace declarations cannot be patched: {path}");
+    }
+
+    Ok(PatchOp {
+        kind,
+        path: path.to_string(),
+        segments,
+        value,
+    })
+}
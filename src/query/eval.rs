@@ -0,0 +1,158 @@
+use crate::query::parse::{Axis, NodeTest, Predicate, Query, Step};
+use crate::xml::tree::{Child, Node, Segment};
+use std::collections::HashSet;
+
+/// A node reached by the query, with the path segments that lead to it.
+#[derive(Clone)]
+struct Ctx<'a> {
+    node: &'a Node,
+    path: Vec<Segment>,
+}
+
+/// Paths of everything `query` matches under `roots`.
+pub fn evaluate(roots: &[Node], query: &Query) -> Vec<Vec<Segment>> {
+    let doc = Node {
+        children: roots.iter().cloned().map(Child::Element).collect(),
+        ..Node::default()
+    };
+    let mut ctxs = vec![Ctx {
+        node: &doc,
+        path: Vec::new(),
+    }];
+
+    let (last, steps) = query
+        .steps
+        .split_last()
+        .expect("parser yields one step or more");
+    for step in steps {
+        ctxs = element_step(&ctxs, step);
+    }
+
+    match &last.test {
+        NodeTest::Attr(_) | NodeTest::AnyAttr | NodeTest::Text => leaf_step(&ctxs, last),
+        _ => element_step(&ctxs, last)
+            .into_iter()
+            .map(|c| c.path)
+            .collect(),
+    }
+}
+
+/// Nodes the step's axis starts from: the context itself, plus every
+/// descendant for `//`.
+fn bases<'a>(ctx: &Ctx<'a>, axis: Axis) -> Vec<Ctx<'a>> {
+    let mut out = vec![ctx.clone()];
+    if axis == Axis::Descendant {
+        let mut i = 0;
+        while i < out.len() {
+            let children = children(&out[i]);
+            out.extend(children);
+            i += 1;
+        }
+    }
+    out
+}
+
+fn children<'a>(ctx: &Ctx<'a>) -> Vec<Ctx<'a>> {
+    ctx.node
+        .children
+        .iter()
+        .filter_map(|c| match c {
+            Child::Element(n) => {
+                let mut path = ctx.path.clone();
+                path.push(Segment::Element {
+                    name: n.name.clone(),
+                    idx: n.idx,
+                });
+                Some(Ctx { node: n, path })
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn element_step<'a>(ctxs: &[Ctx<'a>], step: &Step) -> Vec<Ctx<'a>> {
+    let mut seen = HashSet::new();
+    let mut out = Vec::new();
+    for ctx in ctxs {
+        for base in bases(ctx, step.axis) {
+            let mut matched: Vec<Ctx<'a>> = children(&base)
+                .into_iter()
+                .filter(|c| match &step.test {
+                    NodeTest::Name(name) => &c.node.name == name,
+                    _ => true,
+                })
+                .collect();
+            for pred in &step.predicates {
+                matched = match pred {
+                    Predicate::Position(n) => matched.into_iter().nth(n - 1).into_iter().collect(),
+                    Predicate::Has { rel, value } => matched
+                        .into_iter()
+                        .filter(|c| has(c.node, rel, value.as_deref()))
+                        .collect(),
+                };
+            }
+            for c in matched {
+                if seen.insert(c.path.clone()) {
+                    out.push(c);
+                }
+            }
+        }
+    }
+    out
+}
+
+fn leaf_step(ctxs: &[Ctx<'_>], step: &Step) -> Vec<Vec<Segment>> {
+    let mut seen = HashSet::new();
+    let mut out = Vec::new();
+    for ctx in ctxs {
+        for base in bases(ctx, step.axis) {
+            let mut leaves: Vec<Segment> = Vec::new();
+            match &step.test {
+                NodeTest::Attr(name) => {
+                    if base.node.attrs.iter().any(|(k, _)| k == name) {
+                        leaves.push(Segment::Attr(name.clone()));
+                    }
+                }
+                NodeTest::AnyAttr => {
+                    leaves.extend(
+                        base.node
+                            .attrs
+                            .iter()
+                            .map(|(k, _)| Segment::Attr(k.clone())),
+                    );
+                }
+                _ => {
+                    for c in &base.node.children {
+                        match c {
+                            Child::Text(_) => leaves.push(Segment::Text),
+                            Child::CData(_) => leaves.push(Segment::CData),
+                            _ => {}
+                        }
+                    }
+                }
+            }
+            for leaf in leaves {
+                let mut path = base.path.clone();
+                path.push(leaf);
+                if seen.insert(path.clone()) {
+                    out.push(path);
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Whether `node` has the child element or attribute `rel`, with `value` if given.
+fn has(node: &Node, rel: &str, value: Option<&str>) -> bool {
+    if let Some(attr) = rel.strip_prefix('@') {
+        return node
+            .attrs
+            .iter()
+            .any(|(k, v)| k == attr && value.is_none_or(|want| v == want));
+    }
+    node.children.iter().any(|c| match c {
+        Child::Element(n) if n.name == rel => value.is_none_or(|want| n.text() == want),
+        _ => false,
+    })
+}
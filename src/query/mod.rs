@@ -0,0 +1,39 @@
+//! A small XPath subset evaluated over flattened metadata.
+//!
+//! Supported: absolute (`/Profile/fieldPermissions`) and descendant (`//fields`)
+//! steps, `*`, 1-based positions (`[2]`), predicates on child text or attributes
+//! (`[type="Lookup"]`, `[@name="x"]`, `[required]`) and a final `@attr` or
+//! `text()` step. A query without a leading `/` searches the whole document.
+
+mod eval;
+mod parse;
+
+pub use parse::Query;
+
+use crate::xml::FlatEntry;
+use crate::xml::build_tree;
+use crate::xml::tree::{Segment, parse_path};
+use anyhow::Result;
+use std::collections::HashSet;
+
+/// The entries of every subtree (or attribute/text leaf) matched by `query`,
+/// in document order.
+pub fn select(entries: &[FlatEntry], query: &Query) -> Result<Vec<FlatEntry>> {
+    let roots = build_tree(entries)?;
+    let matches = eval::evaluate(&roots, query);
+    if matches.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // Each entry is kept if one of its path prefixes matched, which costs one
+    // lookup per path segment instead of a scan over every match.
+    let matches: HashSet<Vec<Segment>> = matches.into_iter().collect();
+    let mut out = Vec::new();
+    for e in entries {
+        let segs: Vec<Segment> = parse_path(&e.p)?;
+        if (1..=segs.len()).any(|len| matches.contains(&segs[..len])) {
+            out.push(e.clone());
+        }
+    }
+    Ok(out)
+}
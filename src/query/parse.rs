@@ -0,0 +1,207 @@
+use anyhow::{Result, bail};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    Child,
+    Descendant,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NodeTest {
+    Name(String),
+    AnyElement,
+    Attr(String),
+    AnyAttr,
+    Text,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Predicate {
+    /// `[n]`, 1-based among the nodes the step matched under one parent.
+    Position(usize),
+    /// `[child]`, `[child="v"]`, `[@attr]` or `[@attr="v"]`; `rel` keeps the `@`.
+    Has { rel: String, value: Option<String> },
+}
+
+#[derive(Debug, Clone)]
+pub struct Step {
+    pub axis: Axis,
+    pub test: NodeTest,
+    pub predicates: Vec<Predicate>,
+}
+
+/// A parsed query, e.g. `//fields[type="Lookup"]/referenceTo`.
+#[derive(Debug, Clone)]
+pub struct Query {
+    pub steps: Vec<Step>,
+}
+
+impl Query {
+    pub fn parse(expr: &str) -> Result<Self> {
+        let mut p = Parser { src: expr, pos: 0 };
+        let mut steps = Vec::new();
+
+        // A relative query searches the whole document.
+        let mut axis = if p.eat("//") || !p.eat("/") {
+            Axis::Descendant
+        } else {
+            Axis::Child
+        };
+        loop {
+            let step = p.step(axis)?;
+            let is_leaf = matches!(
+                step.test,
+                NodeTest::Attr(_) | NodeTest::AnyAttr | NodeTest::Text
+            );
+            steps.push(step);
+
+            if p.at_end() {
+                break;
+            }
+            if is_leaf {
+                bail!("`@attr` and `text()` must be the last step in `{expr}`");
+            }
+            axis = if p.eat("//") {
+                Axis::Descendant
+            } else if p.eat("/") {
+                Axis::Child
+            } else {
+                bail!("Expected `/` at offset {} in `{expr}`", p.pos);
+            };
+        }
+        Ok(Self { steps })
+    }
+
+    /// Whether the final step picks attributes (`@attr` or `@*`), so the matches
+    /// are attribute entries rather than subtrees.
+    pub fn selects_attrs(&self) -> bool {
+        self.steps
+            .last()
+            .is_some_and(|s| matches!(s.test, NodeTest::Attr(_) | NodeTest::AnyAttr))
+    }
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn rest(&self) -> &str {
+        &self.src[self.pos..]
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.src.len()
+    }
+
+    fn eat(&mut self, s: &str) -> bool {
+        if self.rest().starts_with(s) {
+            self.pos += s.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_ws(&mut self) {
+        let trimmed = self.rest().trim_start();
+        self.pos = self.src.len() - trimmed.len();
+    }
+
+    /// An element or attribute name (namespace prefixes allowed).
+    fn name(&mut self) -> Result<String> {
+        let len = self
+            .rest()
+            .find(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '-' | ':' | '.')))
+            .unwrap_or(self.rest().len());
+        if len == 0 {
+            bail!("Expected a name at offset {} in `{}`", self.pos, self.src);
+        }
+        let name = self.rest()[..len].to_string();
+        self.pos += len;
+        Ok(name)
+    }
+
+    fn step(&mut self, axis: Axis) -> Result<Step> {
+        let test = if self.eat("text()") {
+            NodeTest::Text
+        } else if self.eat("@*") {
+            NodeTest::AnyAttr
+        } else if self.eat("@") {
+            NodeTest::Attr(self.name()?)
+        } else if self.eat("*") {
+            NodeTest::AnyElement
+        } else {
+            NodeTest::Name(self.name()?)
+        };
+
+        let mut predicates = Vec::new();
+        while self.eat("[") {
+            self.skip_ws();
+            predicates.push(self.predicate()?);
+            self.skip_ws();
+            if !self.eat("]") {
+                bail!("Expected `]` at offset {} in `{}`", self.pos, self.src);
+            }
+        }
+        Ok(Step {
+            axis,
+            test,
+            predicates,
+        })
+    }
+
+    fn predicate(&mut self) -> Result<Predicate> {
+        let digits = self
+            .rest()
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(self.rest().len());
+        if digits > 0 {
+            let n: usize = self.rest()[..digits].parse()?;
+            if n == 0 {
+                bail!("Positions are 1-based in `{}`", self.src);
+            }
+            self.pos += digits;
+            return Ok(Predicate::Position(n));
+        }
+
+        let rel = if self.eat("@") {
+            format!("@{}", self.name()?)
+        } else {
+            self.name()?
+        };
+        self.skip_ws();
+        if !self.eat("=") {
+            return Ok(Predicate::Has { rel, value: None });
+        }
+        self.skip_ws();
+        let value = self.quoted()?;
+        Ok(Predicate::Has {
+            rel,
+            value: Some(value),
+        })
+    }
+
+    fn quoted(&mut self) -> Result<String> {
+        let Some(quote) = self
+            .rest()
+            .chars()
+            .next()
+            .filter(|c| matches!(c, '"' | '\''))
+        else {
+            bail!(
+                "Expected a quoted value at offset {} in `{}`",
+                self.pos,
+                self.src
+            );
+        };
+        self.pos += 1;
+        let Some(len) = self.rest().find(quote) else {
+            bail!("Unterminated string in `{}`", self.src);
+        };
+        let value = self.rest()[..len].to_string();
+        self.pos += len + 1;
+        Ok(value)
+    }
+}
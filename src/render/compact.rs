@@ -1,4 +1,6 @@
+use crate::render::{Doc, Parsed, ParsedDoc, Trailer, budget_lines, parsed_entry, split_location};
 use crate::xml::FlatEntry;
+use anyhow::{Result, bail};
 use std::path::PathBuf;
 
 pub fn render(entries: &[FlatEntry]) -> String {
@@ -6,20 +8,77 @@ pub fn render(entries: &[FlatEntry]) -> String {
     // Minimal escaping to stay machine-parseable.
     let mut parts: Vec<String> = Vec::with_capacity(entries.len());
     for e in entries {
-        parts.push(format!("{}={}", e.p, esc(&e.v)));
+        match e.l {
+            Some(line) => parts.push(format!("L{line} {}={}", e.p, esc(&e.v))),
+            None => parts.push(format!("{}={}", e.p, esc(&e.v))),
+        }
     }
     parts.join("|")
 }
 
-pub fn wrap_many(docs: Vec<(PathBuf, String)>) -> String {
+pub fn wrap_many(docs: &[Doc], trailer: Trailer<'_>) -> String {
     // Still compact, but keep doc boundaries.
-    let mut parts = Vec::with_capacity(docs.len());
-    for (path, body) in docs {
-        parts.push(format!("file={};{}", esc(&path.to_string_lossy()), body));
+    let mut parts = Vec::with_capacity(docs.len() + 2);
+    for doc in docs {
+        parts.push(format!(
+            "file={};{}",
+            esc(&doc.path.to_string_lossy()),
+            doc.body
+        ));
+    }
+    if !trailer.errors().is_empty() {
+        let errs: Vec<String> = trailer
+            .errors()
+            .iter()
+            .map(|e| esc(&e.to_string()))
+            .collect();
+        parts.push(format!("errors={}", errs.join("|")));
+    }
+    if let Some(report) = trailer.budget {
+        let lines: Vec<String> = budget_lines(report).iter().map(|l| esc(l)).collect();
+        parts.push(format!("budget={}", lines.join("|")));
     }
     parts.join("\n")
 }
 
+/// Read compact output (single or wrapped) back into entries, undoing `esc`.
+pub fn parse(text: &str) -> Result<Parsed> {
+    let mut docs = Vec::new();
+    let mut reduced = false;
+
+    for line in text.lines() {
+        if line.starts_with("budget=") {
+            reduced = true;
+            continue;
+        }
+        if line.trim().is_empty() || line.starts_with("errors=") {
+            continue;
+        }
+        let (file, body) = match line.strip_prefix("file=") {
+            Some(rest) => match rest.split_once(';') {
+                Some((file, body)) => (Some(PathBuf::from(unesc(file))), body),
+                None => bail!("Compact wrapper line without `;` after the file name"),
+            },
+            None => (None, line),
+        };
+
+        let mut entries = Vec::new();
+        for part in split_unescaped(body, '|') {
+            if part.is_empty() {
+                continue;
+            }
+            let (l, part) = split_location(part);
+            let Some((p, v)) = part.split_once('=') else {
+                bail!("Compact entry is not `path=value`: {part}");
+            };
+            entries.push(parsed_entry(p, unesc(v), l));
+        }
+        docs.push(ParsedDoc { file, entries });
+    }
+
+    Ok(Parsed { docs, reduced })
+}
+
 fn esc(s: &str) -> String {
     s.replace('\\', "\\\\")
         .replace('|', "\\|")
@@ -27,3 +86,40 @@ fn esc(s: &str) -> String {
         .replace('\n', "\\n")
         .replace('\r', "\\r")
 }
+
+fn unesc(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// Split on `sep` where it is not preceded by an escaping backslash.
+fn split_unescaped(s: &str, sep: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == sep {
+            parts.push(&s[start..i]);
+            start = i + c.len_utf8();
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
@@ -0,0 +1,164 @@
+use crate::parser::{Field, FieldPermission, ObjectPermission};
+use crate::render::{CsvTable, Doc};
+use crate::util::{file_name_stem, object_dir_name};
+use crate::xml::tree::Node;
+use crate::xml::{FlatEntry, build_document};
+use anyhow::Result;
+use serde::Serialize;
+use std::path::Path;
+
+#[derive(Serialize)]
+struct PermissionRow<'a> {
+    file: &'a str,
+    /// `Profile` or `PermissionSet`.
+    kind: &'a str,
+    name: &'a str,
+    object: &'a str,
+    field: &'a str,
+    read: bool,
+    edit: bool,
+    create: Option<bool>,
+    delete: Option<bool>,
+}
+
+#[derive(Serialize)]
+struct FieldRow<'a> {
+    file: &'a str,
+    object: &'a str,
+    field: &'a str,
+    r#type: &'a str,
+    label: &'a str,
+    required: bool,
+    unique: bool,
+    length: Option<u32>,
+}
+
+/// Render one file as CSV rows (with a header) for `table`. Files without
+/// matching sections (e.g. a Flow for `permissions`) give no rows.
+pub fn render(file: &Path, entries: &[FlatEntry], table: CsvTable) -> Result<String> {
+    let doc = build_document(entries)?;
+    let path = file.to_string_lossy();
+    let mut w = csv::Writer::from_writer(Vec::new());
+
+    for root in doc.elements() {
+        let name = root
+            .child_text("fullName")
+            .unwrap_or_else(|| file_name_stem(file));
+        match table {
+            CsvTable::Permissions => {
+                if root.name != "Profile" && root.name != "PermissionSet" {
+                    continue;
+                }
+                for n in root.elements() {
+                    match n.name.as_str() {
+                        "objectPermissions" => {
+                            let p = object_permission(n);
+                            w.serialize(PermissionRow {
+                                file: &path,
+                                kind: &root.name,
+                                name: &name,
+                                object: &p.object,
+                                field: "",
+                                read: p.allow_read,
+                                edit: p.allow_edit,
+                                create: Some(p.allow_create),
+                                delete: Some(p.allow_delete),
+                            })?;
+                        }
+                        "fieldPermissions" => {
+                            let p = field_permission(n);
+                            let (object, field) = p.field.split_once('.').unwrap_or(("", &p.field));
+                            w.serialize(PermissionRow {
+                                file: &path,
+                                kind: &root.name,
+                                name: &name,
+                                object,
+                                field,
+                                read: p.readable,
+                                edit: p.editable,
+                                create: None,
+                                delete: None,
+                            })?;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            CsvTable::Fields => {
+                let fields: Vec<(String, Field)> = match root.name.as_str() {
+                    "CustomObject" => root
+                        .elements()
+                        .filter(|n| n.name == "fields")
+                        .map(|n| (name.clone(), field(n)))
+                        .collect(),
+                    // Source format: objects/<Object>/fields/<Field>.field-meta.xml
+                    "CustomField" => vec![(object_dir_name(file), field(root))],
+                    _ => continue,
+                };
+                for (object, f) in &fields {
+                    w.serialize(FieldRow {
+                        file: &path,
+                        object,
+                        field: &f.name,
+                        r#type: f.field_type.as_deref().unwrap_or_default(),
+                        label: f.label.as_deref().unwrap_or_default(),
+                        required: f.required,
+                        unique: f.unique,
+                        length: f.length,
+                    })?;
+                }
+            }
+        }
+    }
+
+    Ok(String::from_utf8(w.into_inner()?)?)
+}
+
+/// Concatenate the rows of all files under the first header. Skipped files and
+/// budget reductions are only reported on stderr.
+pub fn wrap_many(docs: &[Doc]) -> String {
+    let mut s = String::new();
+    for doc in docs {
+        if s.is_empty() {
+            s.push_str(&doc.body);
+        } else {
+            s.extend(doc.body.split_inclusive('\n').skip(1));
+        }
+    }
+    s
+}
+
+fn object_permission(n: &Node) -> ObjectPermission {
+    ObjectPermission {
+        object: n.child_text("object").unwrap_or_default(),
+        allow_create: child_flag(n, "allowCreate"),
+        allow_read: child_flag(n, "allowRead"),
+        allow_edit: child_flag(n, "allowEdit"),
+        allow_delete: child_flag(n, "allowDelete"),
+    }
+}
+
+fn field_permission(n: &Node) -> FieldPermission {
+    FieldPermission {
+        field: n.child_text("field").unwrap_or_default(),
+        readable: child_flag(n, "readable"),
+        editable: child_flag(n, "editable"),
+    }
+}
+
+fn field(n: &Node) -> Field {
+    Field {
+        name: n.child_text("fullName").unwrap_or_default(),
+        field_type: n.child_text("type"),
+        label: n.child_text("label"),
+        required: child_flag(n, "required"),
+        unique: child_flag(n, "unique"),
+        length: n.child_text("length").and_then(|v| v.parse().ok()),
+        description: n.child_text("description"),
+    }
+}
+
+fn child_flag(node: &Node, name: &str) -> bool {
+    node.child_text(name)
+        .is_some_and(|v| v.eq_ignore_ascii_case("true"))
+}
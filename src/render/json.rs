@@ -1,46 +1,228 @@
-use crate::cli::JsonMode;
-use crate::xml::FlatEntry;
-use anyhow::Result;
+use crate::filter::PathPattern;
+use crate::render::{Doc, JsonMode, Parsed, ParsedDoc, Trailer};
+use crate::xml::tree::{Child, Node};
+use crate::xml::{FlatEntry, ValueKind, build_document};
+use anyhow::{Result, bail};
 use serde_json::{Map, Value, json};
 use std::path::PathBuf;
 
-pub fn render(entries: &[FlatEntry], mode: JsonMode, minify: bool) -> Result<String> {
-    let v = match mode {
-        JsonMode::List => serde_json::to_value(entries)?,
+/// Render entries as JSON. With `typed` (`--typed`), booleans and numbers are
+/// written natively except under the given string-only patterns.
+pub fn render(
+    entries: &[FlatEntry],
+    mode: JsonMode,
+    minify: bool,
+    typed: Option<&[PathPattern]>,
+) -> Result<String> {
+    let v = value(entries, mode, typed)?;
+    Ok(if minify {
+        serde_json::to_string(&v)?
+    } else {
+        serde_json::to_string_pretty(&v)?
+    })
+}
+
+/// The value [`render`] writes out.
+fn value(entries: &[FlatEntry], mode: JsonMode, typed: Option<&[PathPattern]>) -> Result<Value> {
+    Ok(match mode {
+        JsonMode::List if typed.is_none() => serde_json::to_value(entries)?,
+        JsonMode::List => Value::Array(
+            entries
+                .iter()
+                .map(|e| {
+                    let mut v = serde_json::to_value(e)?;
+                    v["v"] = entry_value(e, typed);
+                    Ok(v)
+                })
+                .collect::<Result<_>>()?,
+        ),
         JsonMode::Map => {
             // last-write-wins if duplicate paths; use List if you need lossless output
             let mut m = Map::new();
             for e in entries {
-                m.insert(e.p.clone(), Value::String(e.v.clone()));
+                m.insert(e.p.clone(), entry_value(e, typed));
             }
             Value::Object(m)
         }
+        JsonMode::Tree => {
+            let doc = build_document(entries)?;
+            let mut m = Map::new();
+            for c in doc.children {
+                if let Child::Element(mut root) = c {
+                    // Prefix declarations (`--ns-mode prefix`) belong to the root element.
+                    root.attrs.splice(0..0, doc.attrs.iter().cloned());
+                    let path = format!("/{}[{}]", root.name, root.idx);
+                    m.insert(root.name.clone(), tree_value(&root, &path, typed));
+                }
+            }
+            Value::Object(m)
+        }
+    })
+}
+
+/// An entry's value: a string, or with `--typed` a boolean or number where one fits.
+pub fn entry_value(e: &FlatEntry, typed: Option<&[PathPattern]>) -> Value {
+    match e.k {
+        ValueKind::Text | ValueKind::Attr => leaf_value(&e.p, &e.v, typed),
+        _ => Value::String(e.v.clone()),
+    }
+}
+
+fn leaf_value(path: &str, v: &str, typed: Option<&[PathPattern]>) -> Value {
+    match typed {
+        Some(strings) if !strings.iter().any(|p| p.matches(path)) => typed_value(v),
+        _ => Value::String(v.to_string()),
+    }
+}
+
+/// One element of `--json-mode tree`: its text when it has nothing else, otherwise
+/// an object of `@attr`s, child elements (an array when a name repeats), `#text`
+/// and any `#comment`/`#pi` markup.
+fn tree_value(node: &Node, path: &str, typed: Option<&[PathPattern]>) -> Value {
+    let text = node.text();
+    let text_path = format!("{path}/#text");
+    let has_children = node
+        .children
+        .iter()
+        .any(|c| !matches!(c, Child::Text(_) | Child::CData(_)));
+    if node.attrs.is_empty() && !has_children {
+        return leaf_value(&text_path, &text, typed);
+    }
+
+    let mut m = Map::new();
+    for (k, v) in &node.attrs {
+        m.insert(
+            format!("@{k}"),
+            leaf_value(&format!("{path}/@{k}"), v, typed),
+        );
+    }
+    let mut push = |key: String, v: Value| match m.get_mut(&key) {
+        Some(Value::Array(items)) => items.push(v),
+        Some(first) => *first = Value::Array(vec![first.take(), v]),
+        None => {
+            m.insert(key, v);
+        }
     };
+    for c in &node.children {
+        match c {
+            Child::Element(n) => {
+                let child_path = format!("{path}/{}[{}]", n.name, n.idx);
+                push(n.name.clone(), tree_value(n, &child_path, typed));
+            }
+            Child::Comment(t) => push("#comment".to_string(), Value::String(t.clone())),
+            Child::Pi(t) => push("#pi".to_string(), Value::String(t.clone())),
+            Child::DocType(t) => push("#doctype".to_string(), Value::String(t.clone())),
+            Child::Text(_) | Child::CData(_) => {}
+        }
+    }
+    if !text.is_empty() {
+        m.insert("#text".to_string(), leaf_value(&text_path, &text, typed));
+    }
+    Value::Object(m)
+}
 
-    Ok(if minify {
-        serde_json::to_string(&v)?
-    } else {
-        serde_json::to_string_pretty(&v)?
-    })
+/// `true`/`false` become booleans and numbers become numbers, but only when
+/// writing them back gives the same text (`"007"`, `"1e3"` and `"1.50"` stay strings).
+fn typed_value(s: &str) -> Value {
+    match s {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ => match s.parse::<serde_json::Number>() {
+            Ok(n) if n.to_string() == s => Value::Number(n),
+            _ => Value::String(s.to_string()),
+        },
+    }
 }
 
-pub fn wrap_many(docs: Vec<(PathBuf, String)>, mode: JsonMode, minify: bool) -> Result<String> {
-    // docs already rendered as strings; but for JSON wrapper we re-embed as JSON values.
-    // If you want fully structured, call flatten+render per file into Value instead.
+/// Wrap documents in an array of `{file, data}` objects, with `data` built from
+/// the entries as [`render`] does. With a trailer (`--keep-going`, `--max-tokens`),
+/// the array moves under `files` next to `errors` and/or `budget`.
+pub fn wrap_many(
+    docs: &[Doc],
+    trailer: Trailer<'_>,
+    mode: JsonMode,
+    minify: bool,
+    typed: Option<&[PathPattern]>,
+) -> Result<String> {
     let mut arr = Vec::with_capacity(docs.len());
-    for (path, body) in docs {
+    for doc in docs {
         arr.push(json!({
-            "file": path.to_string_lossy(),
-            "data": match mode {
-                JsonMode::List => serde_json::from_str::<Value>(&body).unwrap_or(Value::String(body)),
-                JsonMode::Map => serde_json::from_str::<Value>(&body).unwrap_or(Value::String(body)),
-            }
+            "file": doc.path.to_string_lossy(),
+            "data": value(&doc.entries, mode, typed)?,
         }));
     }
 
+    let v = if trailer.errors.is_none() && trailer.budget.is_none() {
+        Value::Array(arr)
+    } else {
+        let mut m = Map::new();
+        m.insert("files".to_string(), Value::Array(arr));
+        if let Some(errors) = trailer.errors {
+            m.insert("errors".to_string(), serde_json::to_value(errors)?);
+        }
+        if let Some(report) = trailer.budget {
+            m.insert("budget".to_string(), serde_json::to_value(report)?);
+        }
+        Value::Object(m)
+    };
+
     Ok(if minify {
-        serde_json::to_string(&arr)?
+        serde_json::to_string(&v)?
     } else {
-        serde_json::to_string_pretty(&arr)?
+        serde_json::to_string_pretty(&v)?
     })
 }
+
+/// Read JSON list output back into entries, single or wrapped.
+pub fn parse(text: &str) -> Result<Parsed> {
+    let v: Value = serde_json::from_str(text)?;
+    let reduced = v.get("budget").is_some();
+
+    let wrapped = match &v {
+        Value::Object(m) if m.contains_key("files") => m.get("files"),
+        Value::Array(a) if a.first().is_some_and(|d| d.get("file").is_some()) => Some(&v),
+        _ => None,
+    };
+
+    let docs = match wrapped {
+        Some(files) => {
+            let Some(files) = files.as_array() else {
+                bail!("JSON wrapper `files` is not an array");
+            };
+            files
+                .iter()
+                .map(|d| {
+                    Ok(ParsedDoc {
+                        file: d.get("file").and_then(Value::as_str).map(PathBuf::from),
+                        entries: parse_data(d.get("data").unwrap_or(&Value::Null))?,
+                    })
+                })
+                .collect::<Result<_>>()?
+        }
+        None => vec![ParsedDoc {
+            file: None,
+            entries: parse_data(&v)?,
+        }],
+    };
+    Ok(Parsed { docs, reduced })
+}
+
+fn parse_data(v: &Value) -> Result<Vec<FlatEntry>> {
+    match v {
+        Value::Array(items) => items.iter().map(parse_entry).collect(),
+        // Map and tree output are key-sorted (and map drops duplicates), so element order is gone.
+        Value::Object(_) => {
+            bail!("JSON map or tree output cannot be converted back; use --json-mode list")
+        }
+        _ => bail!("Expected a JSON list of entries"),
+    }
+}
+
+/// Read one `{p, k, v}` object back; values written by `--typed` come back as their text.
+pub fn parse_entry(item: &Value) -> Result<FlatEntry> {
+    let mut item = item.clone();
+    if let Some(v @ (Value::Bool(_) | Value::Number(_))) = item.get_mut("v") {
+        *v = Value::String(v.to_string());
+    }
+    Ok(serde_json::from_value(item)?)
+}
@@ -0,0 +1,205 @@
+use crate::render::{Doc, Trailer, budget_lines};
+use crate::xml::tree::{Child, Node};
+use crate::xml::{FlatEntry, build_document};
+use anyhow::Result;
+use std::path::Path;
+
+/// Columns of the object fields table; other field settings are left out.
+const FIELD_COLUMNS: [&str; 7] = [
+    "fullName",
+    "label",
+    "type",
+    "length",
+    "required",
+    "unique",
+    "referenceTo",
+];
+
+/// Render one file as a Markdown report: the root's simple settings as a list,
+/// repeated sections whose items are flat (permissions, visibilities, ...) as
+/// tables, object fields as a table of their main settings, validation rules with
+/// their formula in a code block, and anything else as a nested bullet list.
+pub fn render(file: &Path, entries: &[FlatEntry]) -> Result<String> {
+    let doc = build_document(entries)?;
+    let name = file
+        .file_name()
+        .map_or_else(|| file.to_string_lossy(), |n| n.to_string_lossy());
+
+    let mut s = String::new();
+    for c in &doc.children {
+        let Child::Element(root) = c else {
+            continue;
+        };
+        s.push_str(&format!("# {} ({})\n", inline(&name), root.name));
+
+        let props: Vec<&Node> = root.elements().filter(|n| is_leaf(n)).collect();
+        if !props.is_empty() {
+            s.push('\n');
+            for n in props {
+                s.push_str(&format!("- **{}**: {}\n", n.name, inline(&n.text())));
+            }
+        }
+
+        for (section, items) in sections(root) {
+            s.push_str(&format!("\n## {section}\n\n"));
+            match section {
+                "validationRules" => validation_rules(&mut s, &items),
+                "fields" => table(&mut s, &items, &FIELD_COLUMNS),
+                _ if items.iter().all(|n| n.elements().all(is_leaf)) => {
+                    // Name columns first, then the flags: a permission matrix.
+                    let mut columns = columns(&items);
+                    columns.sort_by_key(|c| {
+                        items
+                            .iter()
+                            .filter_map(|n| n.child_text(c))
+                            .all(|v| v == "true" || v == "false")
+                    });
+                    table(&mut s, &items, &columns);
+                }
+                _ => {
+                    for n in items {
+                        bullets(&mut s, n, 0);
+                    }
+                }
+            }
+        }
+    }
+    Ok(s)
+}
+
+/// Join reports with a rule between files, then `## Errors` / `## Budget`.
+pub fn wrap_many(docs: &[Doc], trailer: Trailer<'_>) -> String {
+    let mut parts: Vec<String> = docs.iter().map(|d| d.body.clone()).collect();
+    if !trailer.errors().is_empty() {
+        let mut s = String::from("## Errors\n\n");
+        for e in trailer.errors() {
+            s.push_str(&format!("- {}\n", inline(&e.to_string())));
+        }
+        parts.push(s);
+    }
+    if let Some(report) = trailer.budget {
+        let mut s = String::from("## Budget\n\n");
+        for line in budget_lines(report) {
+            s.push_str(&format!("- {}\n", inline(&line)));
+        }
+        parts.push(s);
+    }
+    parts.join("\n---\n\n")
+}
+
+fn is_leaf(node: &Node) -> bool {
+    node.elements().next().is_none()
+}
+
+/// Non-leaf children of `root` grouped by name, in order of first appearance.
+fn sections(root: &Node) -> Vec<(&str, Vec<&Node>)> {
+    let mut out: Vec<(&str, Vec<&Node>)> = Vec::new();
+    for n in root.elements().filter(|n| !is_leaf(n)) {
+        match out.iter_mut().find(|(name, _)| *name == n.name) {
+            Some((_, items)) => items.push(n),
+            None => out.push((&n.name, vec![n])),
+        }
+    }
+    out
+}
+
+/// Child names across all items, in order of first appearance.
+fn columns<'a>(items: &[&'a Node]) -> Vec<&'a str> {
+    let mut out: Vec<&str> = Vec::new();
+    for n in items.iter().flat_map(|n| n.elements()) {
+        if !out.contains(&n.name.as_str()) {
+            out.push(&n.name);
+        }
+    }
+    out
+}
+
+fn table(s: &mut String, items: &[&Node], columns: &[&str]) {
+    // Columns no item has are dropped.
+    let columns: Vec<&str> = columns
+        .iter()
+        .copied()
+        .filter(|c| items.iter().any(|n| n.child_text(c).is_some()))
+        .collect();
+    if columns.is_empty() {
+        for n in items {
+            bullets(s, n, 0);
+        }
+        return;
+    }
+
+    s.push_str(&format!("| {} |\n", columns.join(" | ")));
+    s.push_str(&format!("|{}\n", "---|".repeat(columns.len())));
+    for n in items {
+        let cells: Vec<String> = columns
+            .iter()
+            .map(|c| cell(&n.child_text(c).unwrap_or_default()))
+            .collect();
+        s.push_str(&format!("| {} |\n", cells.join(" | ")));
+    }
+}
+
+fn validation_rules(s: &mut String, items: &[&Node]) {
+    for (i, n) in items.iter().enumerate() {
+        if i > 0 {
+            s.push('\n');
+        }
+        let name = n
+            .child_text("fullName")
+            .unwrap_or_else(|| format!("#{}", i + 1));
+        s.push_str(&format!("### {}\n\n", inline(&name)));
+        for key in ["active", "errorDisplayField", "errorMessage", "description"] {
+            if let Some(v) = n.child_text(key) {
+                s.push_str(&format!("- **{key}**: {}\n", inline(&v)));
+            }
+        }
+        if let Some(formula) = n.child_text("errorConditionFormula") {
+            s.push_str(&format!("\n```\n{}\n```\n", formula.trim_end()));
+        }
+    }
+}
+
+/// Generic fallback: one bullet per element, attributes and text inline.
+fn bullets(s: &mut String, node: &Node, depth: usize) {
+    let indent = "  ".repeat(depth);
+    let mut line = format!("{indent}- **{}**", node.name);
+    for (k, v) in &node.attrs {
+        line.push_str(&format!(" `{k}={v}`"));
+    }
+    let text = node.text();
+    if !text.is_empty() {
+        line.push_str(&format!(": {}", inline(&text)));
+    }
+    s.push_str(&line);
+    s.push('\n');
+    for n in node.elements() {
+        bullets(s, n, depth + 1);
+    }
+}
+
+/// A value on one line, with the characters Markdown would read as emphasis,
+/// code, links, HTML or a table column escaped (`Rev__c` stays `Rev__c`).
+fn inline(v: &str) -> String {
+    let mut out = String::with_capacity(v.len());
+    for (i, word) in v.split_whitespace().enumerate() {
+        if i > 0 {
+            out.push(' ');
+        }
+        for c in word.chars() {
+            if matches!(c, '\\' | '`' | '*' | '_' | '|' | '[' | ']' | '<') {
+                out.push('\\');
+            }
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Table cell: booleans as a check mark (or blank), otherwise an escaped line.
+fn cell(v: &str) -> String {
+    match v {
+        "true" => "✓".to_string(),
+        "false" => String::new(),
+        _ => inline(v),
+    }
+}
@@ -1,7 +1,140 @@
 pub mod compact;
+#[cfg(feature = "csv")]
+pub mod csv;
 pub mod json;
+pub mod markdown;
+pub mod ndjson;
 pub mod toon;
+pub mod yaml;
 
+#[cfg(feature = "csv")]
+pub use self::csv as render_csv;
 pub use compact as render_compact;
 pub use json as render_json;
+pub use markdown as render_markdown;
+pub use ndjson as render_ndjson;
 pub use toon as render_toon;
+pub use yaml as render_yaml;
+
+use crate::budget::BudgetReport;
+use crate::util::FileError;
+use crate::xml::tree::path_segments;
+use crate::xml::{FlatEntry, ValueKind};
+use std::path::PathBuf;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum JsonMode {
+    List,
+    Map,
+    /// Nested objects; repeated elements become arrays
+    Tree,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum YamlMode {
+    /// `path: value` pairs
+    Flat,
+    /// Nested mappings; repeated elements become sequences
+    Tree,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum NdjsonMode {
+    /// One line per entry
+    Entry,
+    /// One line per file, with its entries in an array
+    File,
+}
+
+#[cfg(feature = "csv")]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum CsvTable {
+    /// One row per object or field permission of a profile or permission set
+    Permissions,
+    /// One row per custom object field
+    Fields,
+}
+
+/// One input file after flattening and rendering.
+#[derive(Debug, Clone)]
+pub struct Doc {
+    pub path: PathBuf,
+    pub entries: Vec<FlatEntry>,
+    pub body: String,
+}
+
+/// Sections appended after the documents of wrapped output.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Trailer<'a> {
+    /// Files skipped under `--keep-going`; `Some` (even if empty) whenever it is on.
+    pub errors: Option<&'a [FileError]>,
+    /// What `--max-tokens` removed, if anything.
+    pub budget: Option<&'a BudgetReport>,
+}
+
+impl Trailer<'_> {
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_none_or(<[FileError]>::is_empty) && self.budget.is_none()
+    }
+
+    fn errors(&self) -> &[FileError] {
+        self.errors.unwrap_or_default()
+    }
+}
+
+/// Human-readable lines describing a budget reduction, for the text formats.
+fn budget_lines(report: &BudgetReport) -> Vec<String> {
+    let mut lines = vec![format!(
+        "output reduced from {} to {} tokens to fit --max-tokens {}",
+        report.original_tokens, report.final_tokens, report.limit
+    )];
+    lines.extend(report.reductions.iter().cloned());
+    lines
+}
+
+/// Rendered output read back by `to-xml`.
+#[derive(Debug, Default)]
+pub struct Parsed {
+    pub docs: Vec<ParsedDoc>,
+    /// A `budget` trailer says `--max-tokens` left content out.
+    pub reduced: bool,
+}
+
+/// A document read back from rendered output; `file` is set when the output was wrapped.
+#[derive(Debug, Default)]
+pub struct ParsedDoc {
+    pub file: Option<PathBuf>,
+    pub entries: Vec<FlatEntry>,
+}
+
+/// Rebuild an entry from a rendered `path`/`value` pair; the kind follows from the leaf.
+fn parsed_entry(p: &str, v: String, l: Option<usize>) -> FlatEntry {
+    let k = match path_segments(p).last().copied().unwrap_or_default() {
+        leaf if leaf.starts_with("#xmlns") => ValueKind::Namespace,
+        leaf if leaf.starts_with('@') => ValueKind::Attr,
+        "#comment" => ValueKind::Comment,
+        "#pi" | "#doctype" => ValueKind::Instruction,
+        _ => ValueKind::Text,
+    };
+    FlatEntry {
+        p: p.to_string(),
+        k,
+        v,
+        l,
+    }
+}
+
+/// Split an optional `L<n> ` location prefix (see `--with-locations`) off a line.
+fn split_location(line: &str) -> (Option<usize>, &str) {
+    if let Some(rest) = line.strip_prefix('L')
+        && let Some((num, tail)) = rest.split_once(' ')
+        && let Ok(n) = num.parse()
+    {
+        return (Some(n), tail);
+    }
+    (None, line)
+}
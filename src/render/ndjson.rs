@@ -0,0 +1,154 @@
+use crate::filter::PathPattern;
+use crate::render::json::{entry_value, parse_entry};
+use crate::render::{Doc, NdjsonMode, Parsed, ParsedDoc, Trailer};
+use crate::xml::tree::path_segments;
+use crate::xml::{FlatEntry, ValueKind};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+#[derive(Serialize)]
+struct Entry<'a> {
+    p: &'a str,
+    k: ValueKind,
+    v: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    l: Option<usize>,
+}
+
+impl<'a> Entry<'a> {
+    fn new(e: &'a FlatEntry, typed: Option<&[PathPattern]>) -> Self {
+        Entry {
+            p: &e.p,
+            k: e.k,
+            v: entry_value(e, typed),
+            l: e.l,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct EntryLine<'a> {
+    file: &'a str,
+    r#type: &'a str,
+    #[serde(flatten)]
+    entry: Entry<'a>,
+}
+
+#[derive(Serialize)]
+struct FileLine<'a> {
+    file: &'a str,
+    r#type: &'a str,
+    entries: Vec<Entry<'a>>,
+}
+
+/// Render one file as JSON Lines: a `{file, type, p, k, v}` object per entry, or
+/// with [`NdjsonMode::File`] a single `{file, type, entries}` object. `type` is
+/// the root element name (`CustomObject`, `Profile`, ...).
+pub fn render(
+    file: &Path,
+    entries: &[FlatEntry],
+    mode: NdjsonMode,
+    typed: Option<&[PathPattern]>,
+) -> Result<String> {
+    let file = file.to_string_lossy();
+    let r#type = metadata_type(entries);
+    let mut s = String::new();
+    match mode {
+        NdjsonMode::Entry => {
+            for e in entries {
+                let line = EntryLine {
+                    file: &file,
+                    r#type,
+                    entry: Entry::new(e, typed),
+                };
+                s.push_str(&serde_json::to_string(&line)?);
+                s.push('\n');
+            }
+        }
+        NdjsonMode::File => {
+            let line = FileLine {
+                file: &file,
+                r#type,
+                entries: entries.iter().map(|e| Entry::new(e, typed)).collect(),
+            };
+            s.push_str(&serde_json::to_string(&line)?);
+            s.push('\n');
+        }
+    }
+    Ok(s)
+}
+
+fn metadata_type(entries: &[FlatEntry]) -> &str {
+    entries
+        .iter()
+        .filter_map(|e| path_segments(&e.p).first().copied())
+        .find(|s| !s.starts_with('#'))
+        .map_or("", |s| s.rsplit_once('[').map_or(s, |(name, _)| name))
+}
+
+/// Every line already names its file, so documents are simply concatenated.
+pub fn wrap_many(docs: &[Doc], trailer: Trailer<'_>) -> Result<String> {
+    let mut s: String = docs.iter().map(|d| d.body.as_str()).collect();
+    s.push_str(&trailer_lines(trailer)?);
+    Ok(s)
+}
+
+/// `{"errors": [...]}` and `{"budget": {...}}` lines, when there is anything to say.
+pub fn trailer_lines(trailer: Trailer<'_>) -> Result<String> {
+    let mut s = String::new();
+    if !trailer.errors().is_empty() {
+        s.push_str(&serde_json::to_string(
+            &serde_json::json!({ "errors": trailer.errors() }),
+        )?);
+        s.push('\n');
+    }
+    if let Some(report) = trailer.budget {
+        s.push_str(&serde_json::to_string(
+            &serde_json::json!({ "budget": report }),
+        )?);
+        s.push('\n');
+    }
+    Ok(s)
+}
+
+/// Read JSON Lines (either mode) back into one document per file; trailer lines
+/// are ignored.
+pub fn parse(text: &str) -> Result<Parsed> {
+    let mut docs: Vec<ParsedDoc> = Vec::new();
+    let mut reduced = false;
+
+    for (n, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let v: Value = serde_json::from_str(line)
+            .with_context(|| format!("NDJSON line {} is not valid JSON", n + 1))?;
+        let Some(file) = v.get("file").and_then(Value::as_str) else {
+            reduced |= v.get("budget").is_some();
+            continue;
+        };
+
+        let file = PathBuf::from(file);
+        if docs.last().is_none_or(|d| d.file.as_ref() != Some(&file)) {
+            docs.push(ParsedDoc {
+                file: Some(file),
+                entries: Vec::new(),
+            });
+        }
+        let doc = docs.last_mut().expect("pushed above");
+        match v.get("entries").and_then(Value::as_array) {
+            Some(items) => {
+                for item in items {
+                    doc.entries.push(parse_entry(item)?);
+                }
+            }
+            None => doc
+                .entries
+                .push(parse_entry(&v).with_context(|| format!("NDJSON line {}", n + 1))?),
+        }
+    }
+
+    Ok(Parsed { docs, reduced })
+}
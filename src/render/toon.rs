@@ -1,4 +1,6 @@
+use crate::render::{Doc, Parsed, ParsedDoc, Trailer, budget_lines, parsed_entry, split_location};
 use crate::xml::FlatEntry;
+use anyhow::{Result, bail};
 use std::path::PathBuf;
 
 pub fn render(entries: &[FlatEntry]) -> String {
@@ -6,6 +8,9 @@ pub fn render(entries: &[FlatEntry]) -> String {
     // This is intentionally generic and complete (all elements/attrs present in entries).
     let mut s = String::new();
     for e in entries {
+        if let Some(line) = e.l {
+            s.push_str(&format!("L{line} "));
+        }
         s.push_str(&e.p);
         s.push_str(" = ");
         s.push_str(&escape_value(&e.v));
@@ -14,21 +19,106 @@ pub fn render(entries: &[FlatEntry]) -> String {
     s
 }
 
-pub fn wrap_many(docs: Vec<(PathBuf, String)>) -> String {
+pub fn wrap_many(docs: &[Doc], trailer: Trailer<'_>) -> String {
     let mut s = String::new();
-    for (i, (path, body)) in docs.into_iter().enumerate() {
+    for (i, doc) in docs.iter().enumerate() {
         if i > 0 {
             s.push('\n');
         }
         s.push_str("### file: ");
-        s.push_str(&path.to_string_lossy());
+        s.push_str(&doc.path.to_string_lossy());
+        s.push('\n');
+        s.push_str(&doc.body);
+    }
+    let mut section = |title: &str, lines: Vec<String>| {
+        if !s.is_empty() {
+            s.push('\n');
+        }
+        s.push_str("### ");
+        s.push_str(title);
         s.push('\n');
-        s.push_str(&body);
+        for line in lines {
+            s.push_str(&escape_value(&line));
+            s.push('\n');
+        }
+    };
+    if !trailer.errors().is_empty() {
+        section(
+            "errors",
+            trailer.errors().iter().map(|e| e.to_string()).collect(),
+        );
+    }
+    if let Some(report) = trailer.budget {
+        section("budget", budget_lines(report));
     }
     s
 }
 
+/// Read TOON (single or wrapped) back into entries; `### errors`/`### budget` trailers
+/// are ignored.
+pub fn parse(text: &str) -> Result<Parsed> {
+    let mut docs: Vec<ParsedDoc> = Vec::new();
+    let mut trailer = false;
+    let mut reduced = false;
+
+    for (n, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if line == "### errors" || line == "### budget" {
+            trailer = true;
+            reduced |= line == "### budget";
+        }
+        if trailer {
+            continue;
+        }
+        if let Some(file) = line.strip_prefix("### file: ") {
+            docs.push(ParsedDoc {
+                file: Some(PathBuf::from(file)),
+                entries: Vec::new(),
+            });
+            continue;
+        }
+
+        let (l, line) = split_location(line);
+        let Some((p, v)) = line.split_once(" = ") else {
+            bail!("TOON line {} is not `path = value`: {line}", n + 1);
+        };
+        if docs.is_empty() {
+            docs.push(ParsedDoc::default());
+        }
+        let doc = docs.last_mut().expect("pushed above");
+        doc.entries.push(parsed_entry(p, unescape_value(v), l));
+    }
+
+    Ok(Parsed { docs, reduced })
+}
+
 fn escape_value(v: &str) -> String {
     // Keep it readable; avoid adding many extra tokens.
     v.replace('\n', "\\n").replace('\r', "\\r")
 }
+
+/// Undo [`escape_value`]. Backslashes are not escaped, so any other backslash
+/// (`C:\Temp`, `\d+`) is kept as written; a value that literally contains `\n`
+/// or `\r` cannot be told apart from a line break.
+pub(crate) fn unescape_value(v: &str) -> String {
+    let mut out = String::with_capacity(v.len());
+    let mut chars = v.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some(other) => {
+                out.push('\\');
+                out.push(other);
+            }
+            None => out.push('\\'),
+        }
+    }
+    out
+}
@@ -0,0 +1,312 @@
+use crate::budget::BudgetReport;
+use crate::render::{Doc, Trailer, YamlMode};
+use crate::util::FileError;
+use crate::xml::tree::{Child, Node};
+use crate::xml::{FlatEntry, build_document};
+use anyhow::Result;
+use std::collections::HashMap;
+
+/// Render entries as one YAML document: `path: value` lines, or with
+/// [`YamlMode::Tree`] the same nesting as `--json-mode tree`, in document order.
+/// A path that occurs more than once in flat mode (mixed text) lists its values.
+pub fn render(entries: &[FlatEntry], mode: YamlMode) -> Result<String> {
+    let mut s = String::new();
+    write_node(&mut s, &value(entries, mode)?, 0);
+    Ok(s)
+}
+
+/// A YAML node. Mappings keep insertion order.
+#[derive(Debug, Clone)]
+enum Yaml {
+    /// A string, quoted when it would otherwise read back as something else.
+    Str(String),
+    /// A number, written as is.
+    Plain(String),
+    Seq(Vec<Yaml>),
+    Map(Mapping),
+}
+
+#[derive(Debug, Clone, Default)]
+struct Mapping {
+    entries: Vec<(String, Yaml)>,
+    index: HashMap<String, usize>,
+}
+
+impl Mapping {
+    /// Add `v` under `key`; a key seen before collects its values in a sequence.
+    fn push(&mut self, key: &str, v: Yaml) {
+        match self.index.get(key) {
+            Some(&i) => match &mut self.entries[i].1 {
+                Yaml::Seq(items) => items.push(v),
+                first => {
+                    *first = Yaml::Seq(vec![std::mem::replace(first, Yaml::Seq(Vec::new())), v])
+                }
+            },
+            None => {
+                self.index.insert(key.to_string(), self.entries.len());
+                self.entries.push((key.to_string(), v));
+            }
+        }
+    }
+}
+
+/// The mapping [`render`] writes out.
+fn value(entries: &[FlatEntry], mode: YamlMode) -> Result<Yaml> {
+    let mut m = Mapping::default();
+    match mode {
+        YamlMode::Flat => {
+            for e in entries {
+                m.push(&e.p, Yaml::Str(e.v.clone()));
+            }
+        }
+        YamlMode::Tree => {
+            let doc = build_document(entries)?;
+            for c in doc.children {
+                if let Child::Element(mut root) = c {
+                    // Prefix declarations (`--ns-mode prefix`) belong to the root element.
+                    root.attrs.splice(0..0, doc.attrs.iter().cloned());
+                    m.push(&root.name, tree_value(&root));
+                }
+            }
+        }
+    }
+    Ok(Yaml::Map(m))
+}
+
+fn tree_value(node: &Node) -> Yaml {
+    let text = node.text();
+    let has_children = node
+        .children
+        .iter()
+        .any(|c| !matches!(c, Child::Text(_) | Child::CData(_)));
+    if node.attrs.is_empty() && !has_children {
+        return Yaml::Str(text);
+    }
+
+    let mut m = Mapping::default();
+    for (k, v) in &node.attrs {
+        m.push(&format!("@{k}"), Yaml::Str(v.clone()));
+    }
+    for c in &node.children {
+        match c {
+            Child::Element(n) => m.push(&n.name, tree_value(n)),
+            Child::Comment(t) => m.push("#comment", Yaml::Str(t.clone())),
+            Child::Pi(t) => m.push("#pi", Yaml::Str(t.clone())),
+            Child::DocType(t) => m.push("#doctype", Yaml::Str(t.clone())),
+            Child::Text(_) | Child::CData(_) => {}
+        }
+    }
+    if !text.is_empty() {
+        m.push("#text", Yaml::Str(text));
+    }
+    Yaml::Map(m)
+}
+
+/// Wrap documents as a YAML stream: one `{file, data}` document per input, with
+/// `data` built from its entries as [`render`] does, then one document with
+/// `errors` and/or `budget` when there is a trailer.
+pub fn wrap_many(docs: &[Doc], trailer: Trailer<'_>, mode: YamlMode) -> Result<String> {
+    let mut s = String::new();
+    let mut push = |v: &Yaml| {
+        s.push_str("---\n");
+        write_node(&mut s, v, 0);
+    };
+
+    for doc in docs {
+        let mut m = Mapping::default();
+        m.push("file", Yaml::Str(doc.path.to_string_lossy().into_owned()));
+        m.push("data", value(&doc.entries, mode)?);
+        push(&Yaml::Map(m));
+    }
+
+    if !trailer.is_empty() {
+        let mut m = Mapping::default();
+        if !trailer.errors().is_empty() {
+            m.push(
+                "errors",
+                Yaml::Seq(trailer.errors().iter().map(error_value).collect()),
+            );
+        }
+        if let Some(report) = trailer.budget {
+            m.push("budget", budget_value(report));
+        }
+        push(&Yaml::Map(m));
+    }
+    Ok(s)
+}
+
+/// The fields of `e`, in the order JSON output has them.
+fn error_value(e: &FileError) -> Yaml {
+    let mut m = Mapping::default();
+    m.push("file", Yaml::Str(e.file.to_string_lossy().into_owned()));
+    if let Some(line) = e.line {
+        m.push("line", Yaml::Plain(line.to_string()));
+    }
+    if let Some(column) = e.column {
+        m.push("column", Yaml::Plain(column.to_string()));
+    }
+    if let Some(excerpt) = &e.excerpt {
+        m.push("excerpt", Yaml::Str(excerpt.clone()));
+    }
+    m.push("message", Yaml::Str(e.message.clone()));
+    Yaml::Map(m)
+}
+
+fn budget_value(r: &BudgetReport) -> Yaml {
+    let mut m = Mapping::default();
+    m.push("limit", Yaml::Plain(r.limit.to_string()));
+    m.push(
+        "original_tokens",
+        Yaml::Plain(r.original_tokens.to_string()),
+    );
+    m.push("final_tokens", Yaml::Plain(r.final_tokens.to_string()));
+    m.push(
+        "reductions",
+        Yaml::Seq(r.reductions.iter().cloned().map(Yaml::Str).collect()),
+    );
+    Yaml::Map(m)
+}
+
+/// Write `node` in block style. The cursor is at the start of a line, with
+/// `indent` the column its content starts at.
+fn write_node(out: &mut String, node: &Yaml, indent: usize) {
+    match node {
+        Yaml::Map(m) if !m.entries.is_empty() => write_map(out, m, indent, false),
+        Yaml::Seq(items) if !items.is_empty() => write_seq(out, items, indent, false),
+        _ => write_scalar(out, node, indent),
+    }
+}
+
+/// `inline`: the first entry continues the current line (after `- `).
+fn write_map(out: &mut String, m: &Mapping, indent: usize, inline: bool) {
+    for (i, (k, v)) in m.entries.iter().enumerate() {
+        if i > 0 || !inline {
+            pad(out, indent);
+        }
+        write_flow(out, k);
+        out.push(':');
+        match v {
+            Yaml::Map(m) if !m.entries.is_empty() => {
+                out.push('\n');
+                write_map(out, m, indent + 2, false);
+            }
+            // Sequences under a key are not indented further.
+            Yaml::Seq(items) if !items.is_empty() => {
+                out.push('\n');
+                write_seq(out, items, indent, false);
+            }
+            _ => {
+                out.push(' ');
+                write_scalar(out, v, indent + 2);
+            }
+        }
+    }
+}
+
+fn write_seq(out: &mut String, items: &[Yaml], indent: usize, inline: bool) {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 || !inline {
+            pad(out, indent);
+        }
+        out.push_str("- ");
+        match item {
+            Yaml::Map(m) if !m.entries.is_empty() => write_map(out, m, indent + 2, true),
+            Yaml::Seq(items) if !items.is_empty() => write_seq(out, items, indent + 2, true),
+            _ => write_scalar(out, item, indent + 2),
+        }
+    }
+}
+
+/// A scalar or empty collection, then the line break. Block scalars put their
+/// lines at `indent`.
+fn write_scalar(out: &mut String, node: &Yaml, indent: usize) {
+    match node {
+        Yaml::Str(s) => write_str(out, s, indent),
+        Yaml::Plain(s) => out.push_str(s),
+        Yaml::Seq(_) => out.push_str("[]"),
+        Yaml::Map(_) => out.push_str("{}"),
+    }
+    out.push('\n');
+}
+
+fn pad(out: &mut String, indent: usize) {
+    out.extend(std::iter::repeat_n(' ', indent));
+}
+
+/// `s` as a plain, `|-` block, single- or double-quoted scalar, whichever is the
+/// first that reads back as the same string.
+fn write_str(out: &mut String, s: &str, indent: usize) {
+    if s.contains('\n')
+        && !s.ends_with('\n')
+        && !s.starts_with([' ', '\t'])
+        && !s.chars().any(|c| needs_escape(c) && c != '\n')
+    {
+        out.push_str("|-");
+        for line in s.split('\n') {
+            out.push('\n');
+            if !line.is_empty() {
+                pad(out, indent);
+                out.push_str(line);
+            }
+        }
+    } else {
+        write_flow(out, s);
+    }
+}
+
+/// `s` on one line: plain, single- or double-quoted.
+fn write_flow(out: &mut String, s: &str) {
+    if is_plain_safe(s) {
+        out.push_str(s);
+    } else if !s.chars().any(needs_escape) {
+        out.push('\'');
+        out.push_str(&s.replace('\'', "''"));
+        out.push('\'');
+    } else {
+        out.push('"');
+        for c in s.chars() {
+            match c {
+                '"' => out.push_str("\\\""),
+                '\\' => out.push_str("\\\\"),
+                '\n' => out.push_str("\\n"),
+                '\r' => out.push_str("\\r"),
+                '\t' => out.push_str("\\t"),
+                c if needs_escape(c) => out.push_str(&format!("\\u{:04X}", c as u32)),
+                c => out.push(c),
+            }
+        }
+        out.push('"');
+    }
+}
+
+/// Whether `s` can be written unquoted and still read back as this string, not
+/// as a boolean, number, null or other YAML syntax.
+fn is_plain_safe(s: &str) -> bool {
+    let Some(first) = s.chars().next() else {
+        return false;
+    };
+    if first.is_whitespace()
+        || s.ends_with(char::is_whitespace)
+        || s.ends_with(':')
+        || s.contains(": ")
+        || s.contains(" #")
+        || s.chars().any(needs_escape)
+    {
+        return false;
+    }
+    // Indicators, and anything that could start a number or timestamp.
+    if "-?:,[]{}#&*!|>'\"%@`+.~".contains(first) || first.is_ascii_digit() {
+        return false;
+    }
+    let lower = s.to_ascii_lowercase();
+    !matches!(
+        lower.as_str(),
+        "true" | "false" | "yes" | "no" | "on" | "off" | "y" | "n" | "null" | "=" | "<<"
+    ) && lower.parse::<f64>().is_err()
+}
+
+/// Characters a YAML reader would not take back literally: controls, and the
+/// line separators and byte order mark it treats as breaks or noise.
+fn needs_escape(c: char) -> bool {
+    c.is_control() || matches!(c, '\u{2028}' | '\u{2029}' | '\u{FEFF}')
+}
@@ -11,9 +11,6 @@ pub fn walk_inputs(input: &Path, recursive: bool, glob: &str) -> Result<Vec<Path
         return Ok(vec![]);
     }
 
-    // Minimal glob: only supports "*.xml" / "*.*" / "*suffix"
-    let suffix = glob.strip_prefix('*');
-
     let mut out = Vec::new();
     let walker = if recursive {
         WalkDir::new(input)
@@ -22,24 +19,42 @@ pub fn walk_inputs(input: &Path, recursive: bool, glob: &str) -> Result<Vec<Path
     };
 
     for e in walker.into_iter().filter_map(|e| e.ok()) {
-        if !e.file_type().is_file() {
-            continue;
+        if e.file_type().is_file() && matches_glob(e.path(), glob) {
+            out.push(e.path().to_path_buf());
         }
-        let p = e.path();
-        if let Some(suf) = suffix {
-            if suf.is_empty()
-                || p.file_name()
+    }
+
+    out.sort();
+    Ok(out)
+}
+
+/// Minimal glob: only supports "*.xml" / "*.*" / "*suffix"; a glob that doesn't
+/// start with '*' matches all files.
+pub fn matches_glob(file: &Path, glob: &str) -> bool {
+    match glob.strip_prefix('*') {
+        Some(suf) => {
+            suf.is_empty()
+                || file
+                    .file_name()
                     .and_then(|x| x.to_str())
                     .is_some_and(|name| name.ends_with(suf))
-            {
-                out.push(p.to_path_buf());
-            }
-        } else {
-            // if glob doesn't start with '*', fall back to all files
-            out.push(p.to_path_buf());
         }
+        None => true,
     }
+}
 
-    out.sort();
-    Ok(out)
+/// `Admin` for `Admin.profile-meta.xml`.
+pub fn file_name_stem(file: &Path) -> String {
+    let name = file.file_name().unwrap_or_default().to_string_lossy();
+    name.split('.').next().unwrap_or_default().to_string()
+}
+
+/// `Account` for `objects/Account/fields/Rev__c.field-meta.xml`.
+pub fn object_dir_name(file: &Path) -> String {
+    file.parent()
+        .filter(|p| p.file_name().is_some_and(|n| n == "fields"))
+        .and_then(Path::parent)
+        .and_then(Path::file_name)
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
 }
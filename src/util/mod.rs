@@ -1,5 +1,9 @@
 pub mod fs_walk;
+pub mod report;
 pub mod token;
+pub mod truncate;
 
-pub use fs_walk::walk_inputs;
-pub use token::estimate_tokens_approx;
+pub use fs_walk::{file_name_stem, matches_glob, object_dir_name, walk_inputs};
+pub use report::FileError;
+pub use token::{TokenBreakdown, Tokenizer, count_tokens};
+pub use truncate::{is_truncated, truncate_chars, truncate_tokens};
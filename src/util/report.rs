@@ -0,0 +1,50 @@
+use crate::xml::XmlSyntaxError;
+use serde::Serialize;
+use std::fmt;
+use std::path::PathBuf;
+
+/// A file skipped under `--keep-going`, with the parse position when one is known.
+#[derive(Debug, Clone, Serialize)]
+pub struct FileError {
+    pub file: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub excerpt: Option<String>,
+    pub message: String,
+}
+
+impl FileError {
+    pub fn from_error(file: PathBuf, err: &anyhow::Error) -> Self {
+        let syntax = err.chain().find_map(|e| e.downcast_ref::<XmlSyntaxError>());
+
+        match syntax {
+            Some(s) => Self {
+                file,
+                line: Some(s.line),
+                column: Some(s.column),
+                excerpt: Some(s.excerpt.clone()).filter(|x| !x.is_empty()),
+                message: s.message.clone(),
+            },
+            None => Self {
+                file,
+                line: None,
+                column: None,
+                excerpt: None,
+                message: err.root_cause().to_string(),
+            },
+        }
+    }
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.file.display())?;
+        if let (Some(line), Some(col)) = (self.line, self.column) {
+            write!(f, ":{line}:{col}")?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
@@ -1,4 +1,82 @@
+use crate::xml::FlatEntry;
+#[cfg(feature = "tokens")]
+use tiktoken_rs::{cl100k_base_singleton, o200k_base_singleton};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Tokenizer {
+    /// chars / 4; fast, model-agnostic
+    Approx,
+    /// GPT-4 / GPT-3.5 BPE
+    #[cfg(feature = "tokens")]
+    Cl100k,
+    /// GPT-4o BPE
+    #[cfg(feature = "tokens")]
+    O200k,
+}
+
+impl Tokenizer {
+    pub fn name(self) -> &'static str {
+        match self {
+            Tokenizer::Approx => "approx",
+            #[cfg(feature = "tokens")]
+            Tokenizer::Cl100k => "cl100k",
+            #[cfg(feature = "tokens")]
+            Tokenizer::O200k => "o200k",
+        }
+    }
+}
+
 pub fn estimate_tokens_approx(s: &str) -> usize {
     // Very rough, model-dependent; good enough for quick comparisons.
     s.chars().count() / 4
 }
+
+/// Count tokens with the selected tokenizer. The BPE tables (`tokens` feature)
+/// ship inside the binary, so this works offline.
+pub fn count_tokens(s: &str, tokenizer: Tokenizer) -> usize {
+    match tokenizer {
+        Tokenizer::Approx => estimate_tokens_approx(s),
+        #[cfg(feature = "tokens")]
+        Tokenizer::Cl100k => cl100k_base_singleton().encode_ordinary(s).len(),
+        #[cfg(feature = "tokens")]
+        Tokenizer::O200k => o200k_base_singleton().encode_ordinary(s).len(),
+    }
+}
+
+/// Where the tokens of a rendered document go.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokenBreakdown {
+    pub total: usize,
+    pub paths: usize,
+    pub values: usize,
+}
+
+impl TokenBreakdown {
+    pub fn new(rendered: &str, entries: &[FlatEntry], tokenizer: Tokenizer) -> Self {
+        let paths: Vec<&str> = entries.iter().map(|e| e.p.as_str()).collect();
+        let values: Vec<&str> = entries.iter().map(|e| e.v.as_str()).collect();
+        Self {
+            total: count_tokens(rendered, tokenizer),
+            paths: count_tokens(&paths.join("\n"), tokenizer),
+            values: count_tokens(&values.join("\n"), tokenizer),
+        }
+    }
+
+    pub fn add(&mut self, other: &TokenBreakdown) {
+        self.paths += other.paths;
+        self.values += other.values;
+    }
+
+    /// One-line summary, e.g. `1234 tokens (cl100k): paths 800, values 380, syntax 54`.
+    pub fn summary(&self, tokenizer: Tokenizer) -> String {
+        format!(
+            "{} tokens ({}): paths {}, values {}, syntax {}",
+            self.total,
+            tokenizer.name(),
+            self.paths,
+            self.values,
+            self.total.saturating_sub(self.paths + self.values)
+        )
+    }
+}
@@ -0,0 +1,65 @@
+use crate::util::{Tokenizer, count_tokens};
+
+/// Cut `s` to at most `max` chars, on a char boundary, appending a marker such as
+/// `…[+1234 chars]`. A value that was already cut keeps one marker with the
+/// combined count. Returns whether anything was cut.
+pub fn truncate_chars(s: &mut String, max: usize) -> bool {
+    let (body_len, earlier) = split_char_marker(s);
+    let Some((cut, _)) = s[..body_len].char_indices().nth(max) else {
+        return false;
+    };
+    let removed = s[cut..body_len].chars().count() + earlier;
+    s.truncate(cut);
+    s.push_str(&format!("…[+{removed} chars]"));
+    true
+}
+
+/// Length of `s` without a trailing `…[+N chars]` marker, and that `N`.
+fn split_char_marker(s: &str) -> (usize, usize) {
+    if let Some(head) = s.strip_suffix(" chars]")
+        && let Some(at) = head.rfind("…[+")
+        && let Ok(n) = head[at + "…[+".len()..].parse()
+    {
+        return (at, n);
+    }
+    (s.len(), 0)
+}
+
+/// Whether `s` ends in a `…[+N chars]` or `…[+N tokens]` marker.
+pub fn is_truncated(s: &str) -> bool {
+    let Some(head) = s
+        .strip_suffix(" chars]")
+        .or_else(|| s.strip_suffix(" tokens]"))
+    else {
+        return false;
+    };
+    head.rfind("…[+")
+        .is_some_and(|at| head[at + "…[+".len()..].parse::<usize>().is_ok())
+}
+
+/// Cut `s` to at most `max` tokens of `tokenizer`, on a char boundary, appending
+/// a marker such as `…[+56 tokens]`. Returns whether anything was cut.
+pub fn truncate_tokens(s: &mut String, max: usize, tokenizer: Tokenizer) -> bool {
+    let total = count_tokens(s, tokenizer);
+    if total <= max {
+        return false;
+    }
+
+    // Longest prefix (in chars) that fits.
+    let bounds: Vec<usize> = s.char_indices().map(|(i, _)| i).collect();
+    let end = |chars: usize| bounds.get(chars).copied().unwrap_or(s.len());
+    let (mut lo, mut hi) = (0, bounds.len());
+    while lo < hi {
+        let mid = (lo + hi).div_ceil(2);
+        if count_tokens(&s[..end(mid)], tokenizer) <= max {
+            lo = mid;
+        } else {
+            hi = mid - 1;
+        }
+    }
+    let cut = end(lo);
+    let removed = total.saturating_sub(count_tokens(&s[..cut], tokenizer));
+    s.truncate(cut);
+    s.push_str(&format!("…[+{removed} tokens]"));
+    true
+}
@@ -0,0 +1,177 @@
+//! `--watch`: render every input into `--output-dir`, then keep that mirror up to
+//! date as files are saved, added, renamed or deleted.
+
+use crate::app::process_one;
+use crate::cache::Cache;
+use crate::cli::Cli;
+use crate::filter::{EntryFilter, PathPattern};
+use crate::mirror;
+use crate::util::{FileError, matches_glob, walk_inputs};
+use crate::xml::FlattenOptions;
+use anyhow::{Context, Result};
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// Editors save in several steps (temp file, rename, chmod); events closer together
+/// than this are handled as one change.
+const SETTLE: Duration = Duration::from_millis(200);
+
+struct Mirror<'a> {
+    cli: &'a Cli,
+    input: &'a Path,
+    dir: &'a Path,
+    opts: &'a FlattenOptions,
+    filter: &'a EntryFilter,
+    typed: Option<&'a [PathPattern]>,
+    cache: Option<Cache>,
+}
+
+impl Mirror<'_> {
+    /// Render `path` into its mirror file. A file that fails to parse (often one
+    /// caught mid-save) is reported and its previous mirror is left in place.
+    fn update(&mut self, path: &Path) -> bool {
+        let out = mirror::mirror_path(self.input, path, self.dir, self.cli.format.extension());
+        let doc = process_one(
+            path,
+            self.cli,
+            self.opts,
+            self.filter,
+            self.typed,
+            self.cache.as_mut(),
+        );
+        match doc.and_then(|doc| mirror::write(&out, &doc.body)) {
+            Ok(()) => true,
+            Err(err) => {
+                eprintln!(
+                    "skipped {}",
+                    FileError::from_error(path.to_path_buf(), &err)
+                );
+                false
+            }
+        }
+    }
+
+    /// Remove the mirror of a deleted file, or of a whole deleted directory.
+    fn remove(&self, path: &Path) {
+        let out = mirror::mirror_path(self.input, path, self.dir, self.cli.format.extension());
+        if out.is_file() {
+            let _ = std::fs::remove_file(&out);
+            eprintln!("removed {}", out.display());
+        } else if let Some(sub) = mirror::mirror_dir(self.input, path, self.dir)
+            && sub.is_dir()
+        {
+            let _ = std::fs::remove_dir_all(&sub);
+            eprintln!("removed {}", sub.display());
+        }
+    }
+}
+
+pub fn run(
+    cli: &Cli,
+    input: &Path,
+    opts: &FlattenOptions,
+    filter: &EntryFilter,
+    typed: Option<&[PathPattern]>,
+    cache: Option<Cache>,
+) -> Result<()> {
+    let dir = cli
+        .output_dir
+        .as_deref()
+        .expect("clap requires --output-dir with --watch");
+    let mut mirror = Mirror {
+        cli,
+        input,
+        dir,
+        opts,
+        filter,
+        typed,
+        cache,
+    };
+
+    let inputs = walk_inputs(input, cli.recursive, &cli.glob)
+        .with_context(|| format!("Failed to enumerate input path(s): {}", input.display()))?;
+    let written = inputs.iter().filter(|p| mirror.update(p)).count();
+    eprintln!("Wrote {written} file(s) to {}", dir.display());
+    if let Some(cache) = &mirror.cache {
+        eprintln!("{}", cache.summary());
+    }
+
+    // A single file is watched through its directory: editors often replace the
+    // file on save, which would end a watch on the file itself.
+    let (watched, mode) = if input.is_file() {
+        (
+            input.parent().unwrap_or(Path::new(".")),
+            RecursiveMode::NonRecursive,
+        )
+    } else if cli.recursive {
+        (input, RecursiveMode::Recursive)
+    } else {
+        (input, RecursiveMode::NonRecursive)
+    };
+    let watched = if watched.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        watched
+    };
+    // Event paths are absolute; they are mapped back onto `input` as given.
+    let root = watched
+        .canonicalize()
+        .with_context(|| format!("Failed to watch: {}", watched.display()))?;
+    let out_root = dir.canonicalize().ok();
+    let single = input
+        .is_file()
+        .then(|| root.join(input.file_name().unwrap_or_default()));
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    watcher
+        .watch(&root, mode)
+        .with_context(|| format!("Failed to watch: {}", watched.display()))?;
+    eprintln!("Watching {} for changes (Ctrl-C to stop)", input.display());
+
+    while let Ok(first) = rx.recv() {
+        let mut changed: BTreeSet<PathBuf> = BTreeSet::new();
+        let mut collect = |res: notify::Result<notify::Event>| match res {
+            Ok(event) => {
+                if matches!(
+                    event.kind,
+                    EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+                ) {
+                    changed.extend(event.paths);
+                }
+            }
+            Err(err) => eprintln!("warning: watch error: {err}"),
+        };
+        collect(first);
+        while let Ok(res) = rx.recv_timeout(SETTLE) {
+            collect(res);
+        }
+
+        for abs in changed {
+            // The output directory may live inside the watched tree.
+            if out_root.as_ref().is_some_and(|o| abs.starts_with(o)) {
+                continue;
+            }
+            let Ok(rel) = abs.strip_prefix(&root) else {
+                continue;
+            };
+            let path = match &single {
+                Some(target) if abs != *target => continue,
+                Some(_) => input.to_path_buf(),
+                None => input.join(rel),
+            };
+
+            if path.is_file() {
+                if matches_glob(&path, &cli.glob) && mirror.update(&path) {
+                    eprintln!("updated {}", path.display());
+                }
+            } else if !path.exists() {
+                mirror.remove(&path);
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,76 @@
+use crate::xml::position::{line_at, line_col};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Longest excerpt (in chars) quoted from the offending source line.
+const EXCERPT_WIDTH: usize = 60;
+
+/// A syntax error raised while flattening, with the offending position resolved to a
+/// 1-based line and column plus a short excerpt of the source line.
+#[derive(Debug, Clone)]
+pub struct XmlSyntaxError {
+    pub file: Option<PathBuf>,
+    pub line: usize,
+    pub column: usize,
+    pub excerpt: String,
+    pub message: String,
+}
+
+impl XmlSyntaxError {
+    pub fn new(xml: &str, offset: u64, message: impl Into<String>) -> Self {
+        let offset = offset as usize;
+        let (line, column) = line_col(xml, offset);
+        Self {
+            file: None,
+            line,
+            column,
+            excerpt: excerpt(line_at(xml, offset), column),
+            message: message.into(),
+        }
+    }
+
+    pub fn with_file(mut self, path: &Path) -> Self {
+        self.file = Some(path.to_path_buf());
+        self
+    }
+
+    /// `file:line:col` (or `line:col` when the source is not a file).
+    pub fn location(&self) -> String {
+        match &self.file {
+            Some(file) => format!("{}:{}:{}", file.display(), self.line, self.column),
+            None => format!("{}:{}", self.line, self.column),
+        }
+    }
+}
+
+impl fmt::Display for XmlSyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: XML parse error: {}", self.location(), self.message)?;
+        if !self.excerpt.is_empty() {
+            write!(f, "\n    {}", self.excerpt)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for XmlSyntaxError {}
+
+/// Window of at most `EXCERPT_WIDTH` chars of `line` around `column`, with `...`
+/// marking elided ends.
+fn excerpt(line: &str, column: usize) -> String {
+    let chars: Vec<char> = line.chars().collect();
+    let start = column
+        .saturating_sub(EXCERPT_WIDTH / 2)
+        .min(chars.len().saturating_sub(EXCERPT_WIDTH));
+    let end = (start + EXCERPT_WIDTH).min(chars.len());
+
+    let mut s: String = chars[start..end].iter().collect();
+    s = s.trim().to_string();
+    if start > 0 && !s.is_empty() {
+        s.insert_str(0, "...");
+    }
+    if end < chars.len() {
+        s.push_str("...");
+    }
+    s
+}
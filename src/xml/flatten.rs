@@ -1,8 +1,12 @@
-use crate::cli::PathStyle;
-use crate::xml::FlattenOptions;
+use crate::util::{truncate_chars, truncate_tokens};
+use crate::xml::position::LineTracker;
+use crate::xml::write::METADATA_NS;
+use crate::xml::{FlattenOptions, NsMode, PathStyle, XmlSyntaxError};
 use anyhow::{Context, Result};
-use quick_xml::Reader;
-use quick_xml::events::{BytesStart, Event};
+use quick_xml::NsReader;
+use quick_xml::escape::resolve_predefined_entity;
+use quick_xml::events::{BytesRef, BytesStart, Event};
+use quick_xml::name::{Namespace, PrefixDeclaration, QName, ResolveResult};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs::File;
@@ -14,18 +18,59 @@ pub struct FlatEntry {
     pub p: String,    // path
     pub k: ValueKind, // kind
     pub v: String,    // value
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub l: Option<usize>, // source line (only with `with_locations`)
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum ValueKind {
     Text,
     Attr,
+    /// `<!-- ... -->` (`#comment` leaf, with `include_comments`).
+    Comment,
+    /// `<?target ...?>` (`#pi` leaf) or `<!DOCTYPE ...>` (`#doctype` leaf).
+    Instruction,
+    /// A prefix declaration (`#xmlns:xsi` leaf), listed once per document in
+    /// `NsMode::Prefix` and `NsMode::Clark`.
+    Namespace,
 }
 
 #[derive(Debug)]
 struct Frame {
     name: String,
     idx: usize,
+    line: Option<usize>,
+    /// Whitespace in this element's text is kept as written (`preserve_space`).
+    preserve: bool,
+}
+
+/// Adjacent text, CDATA and entity-reference events, joined into one value.
+#[derive(Debug, Default)]
+struct TextRun {
+    text: String,
+    /// Some non-blank part came from plain text or an entity (not CDATA).
+    plain: bool,
+    /// Some non-blank part came from a CDATA section.
+    cdata: bool,
+    /// Source line of the first non-blank part (only with `with_locations`).
+    line: Option<usize>,
+}
+
+impl TextRun {
+    /// `line` gives the source line of `s`'s first non-blank character.
+    fn push(&mut self, s: &str, cdata: bool, line: impl FnOnce() -> Option<usize>) {
+        if !s.trim().is_empty() {
+            if self.line.is_none() {
+                self.line = line();
+            }
+            if cdata {
+                self.cdata = true;
+            } else {
+                self.plain = true;
+            }
+        }
+        self.text.push_str(s);
+    }
 }
 
 pub fn flatten_xml_file(path: &Path, opts: &FlattenOptions) -> Result<Vec<FlatEntry>> {
@@ -36,12 +81,16 @@ pub fn flatten_xml_file(path: &Path, opts: &FlattenOptions) -> Result<Vec<FlatEn
     f.read_to_string(&mut s)
         .with_context(|| format!("Failed reading file as UTF-8 text: {}", path.display()))?;
 
-    flatten_xml_str(&s, opts).with_context(|| format!("Failed flattening: {}", path.display()))
+    flatten_xml_str(&s, opts).map_err(|err| match err.downcast::<XmlSyntaxError>() {
+        Ok(syntax) => syntax.with_file(path).into(),
+        Err(err) => err.context(format!("Failed flattening: {}", path.display())),
+    })
 }
 
 pub fn flatten_xml_str(xml: &str, opts: &FlattenOptions) -> Result<Vec<FlatEntry>> {
-    let mut reader = Reader::from_str(xml);
-    reader.config_mut().trim_text(true);
+    // Text is not trimmed per event: an element's value may arrive as several
+    // text, CDATA and entity events, which are joined before trimming.
+    let mut reader = NsReader::from_str(xml);
 
     let mut out: Vec<FlatEntry> = Vec::new();
     let mut buf: Vec<u8> = Vec::new();
@@ -49,17 +98,58 @@ pub fn flatten_xml_str(xml: &str, opts: &FlattenOptions) -> Result<Vec<FlatEntry
     let mut stack: Vec<Frame> = Vec::new();
     let mut child_counts: Vec<HashMap<String, usize>> = Vec::new();
     child_counts.push(HashMap::new()); // root-level counts
+    let mut lines = LineTracker::new();
+    let mut run = TextRun::default();
+    let mut prefixes: Vec<(String, String)> = Vec::new();
 
     loop {
-        match reader.read_event_into(&mut buf) {
+        let start = reader.buffer_position() as usize;
+        let event = reader.read_event_into(&mut buf);
+        match &event {
+            Ok(Event::Text(_) | Event::CData(_) | Event::GeneralRef(_)) => {}
+            Ok(Event::Comment(_) | Event::PI(_)) if !opts.include_comments => {}
+            // Any other event ends the current run of text. At an end tag the run
+            // is the element's whole content only if the element has no children.
+            _ => {
+                let edges = Edges {
+                    after_child: child_counts.last().is_some_and(|c| !c.is_empty()),
+                    before_child: matches!(event, Ok(Event::Start(_) | Event::Empty(_))),
+                };
+                let leaf = matches!(event, Ok(Event::End(_))) && !edges.after_child;
+                flush_text(
+                    &mut out,
+                    &stack,
+                    opts,
+                    std::mem::take(&mut run),
+                    leaf,
+                    edges,
+                );
+            }
+        }
+
+        match event {
             Ok(Event::Start(e)) => {
-                push_element(&mut stack, &mut child_counts, &e, opts)?;
+                let line = opts
+                    .with_locations
+                    .then(|| lines.line_at(xml, tag_end(&reader)));
+                let name = element_name(&reader, &e, opts.ns_mode);
+                push_element(&mut stack, &mut child_counts, name, opts, line);
+                if opts.ns_mode != NsMode::Strip {
+                    collect_prefixes(&e, &mut prefixes)?;
+                }
                 if opts.include_attributes {
                     push_attrs(&mut out, &stack, &e, opts, &reader)?;
                 }
             }
             Ok(Event::Empty(e)) => {
-                push_element(&mut stack, &mut child_counts, &e, opts)?;
+                let line = opts
+                    .with_locations
+                    .then(|| lines.line_at(xml, tag_end(&reader)));
+                let name = element_name(&reader, &e, opts.ns_mode);
+                push_element(&mut stack, &mut child_counts, name, opts, line);
+                if opts.ns_mode != NsMode::Strip {
+                    collect_prefixes(&e, &mut prefixes)?;
+                }
                 if opts.include_attributes {
                     push_attrs(&mut out, &stack, &e, opts, &reader)?;
                 }
@@ -69,30 +159,72 @@ pub fn flatten_xml_str(xml: &str, opts: &FlattenOptions) -> Result<Vec<FlatEntry
             }
             Ok(Event::Text(e)) => {
                 if opts.include_text {
-                    let mut text = e.decode()?.to_string();
-                    text = normalize_text(text, opts.max_text_len);
-                    if !text.is_empty() {
-                        out.push(FlatEntry {
-                            p: make_path(&stack, opts.path_style, Some("#text")),
-                            k: ValueKind::Text,
-                            v: text,
-                        });
-                    }
+                    let offset = start + leading_space(e.as_ref());
+                    run.push(&e.xml_content()?, false, || {
+                        opts.with_locations.then(|| lines.line_at(xml, offset))
+                    });
                 }
             }
             Ok(Event::CData(e)) => {
                 if opts.include_cdata {
-                    let mut text = String::from_utf8_lossy(e.as_ref()).to_string();
-                    text = normalize_text(text, opts.max_text_len);
-                    if !text.is_empty() {
-                        out.push(FlatEntry {
-                            p: make_path(&stack, opts.path_style, Some("#cdata")),
-                            k: ValueKind::Text,
-                            v: text,
-                        });
-                    }
+                    let offset = start + "<![CDATA[".len() + leading_space(e.as_ref());
+                    run.push(&String::from_utf8_lossy(e.as_ref()), true, || {
+                        opts.with_locations.then(|| lines.line_at(xml, offset))
+                    });
+                }
+            }
+            Ok(Event::GeneralRef(e)) => {
+                if opts.include_text {
+                    run.push(&resolve_entity(&e)?, false, || {
+                        opts.with_locations.then(|| lines.line_at(xml, start))
+                    });
                 }
             }
+            Ok(Event::Comment(e)) if opts.include_comments => {
+                let text = String::from_utf8_lossy(e.as_ref()).trim().to_string();
+                let line = opts
+                    .with_locations
+                    .then(|| lines.line_at(xml, tag_end(&reader)));
+                push_markup(
+                    &mut out,
+                    &stack,
+                    opts,
+                    "#comment",
+                    ValueKind::Comment,
+                    text,
+                    line,
+                );
+            }
+            Ok(Event::PI(e)) if opts.include_comments => {
+                let text = String::from_utf8_lossy(e.as_ref()).trim().to_string();
+                let line = opts
+                    .with_locations
+                    .then(|| lines.line_at(xml, tag_end(&reader)));
+                push_markup(
+                    &mut out,
+                    &stack,
+                    opts,
+                    "#pi",
+                    ValueKind::Instruction,
+                    text,
+                    line,
+                );
+            }
+            Ok(Event::DocType(e)) if opts.include_comments => {
+                let text = String::from_utf8_lossy(e.as_ref()).trim().to_string();
+                let line = opts
+                    .with_locations
+                    .then(|| lines.line_at(xml, tag_end(&reader)));
+                push_markup(
+                    &mut out,
+                    &stack,
+                    opts,
+                    "#doctype",
+                    ValueKind::Instruction,
+                    text,
+                    line,
+                );
+            }
             Ok(Event::End(_e)) => {
                 stack.pop();
                 child_counts.pop();
@@ -100,35 +232,89 @@ pub fn flatten_xml_str(xml: &str, opts: &FlattenOptions) -> Result<Vec<FlatEntry
             Ok(Event::Eof) => break,
             Ok(_) => {}
             Err(err) => {
-                return Err(anyhow::anyhow!(
-                    "XML parse error at position {}: {}",
-                    reader.error_position(),
-                    err
-                ));
+                return Err(
+                    XmlSyntaxError::new(xml, reader.error_position(), err.to_string()).into(),
+                );
             }
         }
         buf.clear();
     }
 
+    // The prefix map goes first so every prefixed name below can be looked up. Clark
+    // mode keeps it too: prefixes can also appear inside values (`xsi:type="xsd:string"`).
+    let map = prefixes.into_iter().map(|(name, uri)| FlatEntry {
+        p: make_path(&[], opts.path_style, Some(&format!("#{name}"))),
+        k: ValueKind::Namespace,
+        v: uri,
+        l: None,
+    });
+    out.splice(0..0, map);
+
     Ok(out)
 }
 
+/// Name of an element or attribute as `ns_mode` asks for it. The Metadata API
+/// namespace is implied and never spelled out. Strip mode only drops element
+/// prefixes: attributes keep their qualified name, so `xsi:type` and `type` stay
+/// apart.
+fn resolve_name(
+    reader: &NsReader<&[u8]>,
+    qname: QName<'_>,
+    attribute: bool,
+    ns_mode: NsMode,
+) -> String {
+    let (ns, local) = if attribute {
+        reader.resolver().resolve_attribute(qname)
+    } else {
+        reader.resolver().resolve_element(qname)
+    };
+    let local = String::from_utf8_lossy(local.as_ref()).into_owned();
+    match (ns_mode, ns) {
+        (NsMode::Strip, _) if !attribute => local,
+        (NsMode::Strip | NsMode::Prefix, _) | (NsMode::Clark, ResolveResult::Unknown(_)) => {
+            String::from_utf8_lossy(qname.as_ref()).into_owned()
+        }
+        (NsMode::Clark, ResolveResult::Bound(Namespace(uri))) if uri != METADATA_NS.as_bytes() => {
+            format!("{{{}}}{local}", String::from_utf8_lossy(uri))
+        }
+        (NsMode::Clark, _) => local,
+    }
+}
+
+fn element_name(reader: &NsReader<&[u8]>, e: &BytesStart<'_>, ns_mode: NsMode) -> String {
+    resolve_name(reader, e.name(), false, ns_mode)
+}
+
+/// Record the `xmlns`/`xmlns:p` declarations of a start tag (first binding of each
+/// prefix wins), skipping the Metadata API default namespace.
+fn collect_prefixes(e: &BytesStart<'_>, prefixes: &mut Vec<(String, String)>) -> Result<()> {
+    for a in e.attributes().with_checks(false) {
+        let a = a?;
+        let Some(decl) = a.key.as_namespace_binding() else {
+            continue;
+        };
+        let uri = String::from_utf8_lossy(&a.value).into_owned();
+        if uri == METADATA_NS {
+            continue;
+        }
+        let name = match decl {
+            PrefixDeclaration::Default => "xmlns".to_string(),
+            PrefixDeclaration::Named(p) => format!("xmlns:{}", String::from_utf8_lossy(p)),
+        };
+        if !prefixes.iter().any(|(n, _)| *n == name) {
+            prefixes.push((name, uri));
+        }
+    }
+    Ok(())
+}
+
 fn push_element(
     stack: &mut Vec<Frame>,
     child_counts: &mut Vec<HashMap<String, usize>>,
-    e: &BytesStart<'_>,
+    name: String,
     opts: &FlattenOptions,
-) -> Result<()> {
-    let binding = e.name();
-    let raw = binding.as_ref();
-    let mut name = String::from_utf8_lossy(raw).to_string();
-
-    if opts.strip_namespace_prefix
-        && let Some(i) = name.rfind(':')
-    {
-        name = name[i + 1..].to_string();
-    }
-
+    line: Option<usize>,
+) {
     let parent_map = child_counts
         .last_mut()
         .expect("child_counts always has at least one frame");
@@ -136,12 +322,15 @@ fn push_element(
     let current_idx = *idx;
     *idx += 1;
 
+    let preserve =
+        opts.preserve_space.contains(&name) || stack.last().is_some_and(|parent| parent.preserve);
     stack.push(Frame {
         name,
         idx: current_idx,
+        line,
+        preserve,
     });
     child_counts.push(HashMap::new());
-    Ok(())
 }
 
 fn push_attrs(
@@ -149,27 +338,147 @@ fn push_attrs(
     stack: &[Frame],
     e: &BytesStart<'_>,
     opts: &FlattenOptions,
-    reader: &Reader<&[u8]>,
+    reader: &NsReader<&[u8]>,
 ) -> Result<()> {
     for a in e.attributes().with_checks(false) {
         let a = a?;
-        let key = String::from_utf8_lossy(a.key.as_ref()).to_string();
-        let val = a.decode_and_unescape_value(reader.decoder())?.to_string();
-        let val = normalize_text(val, opts.max_text_len);
+        // The Metadata API default is implied; other declarations are listed in the
+        // prefix map, or in strip mode kept as plain `@xmlns:p` attributes.
+        if a.key.as_namespace_binding().is_some()
+            && (opts.ns_mode != NsMode::Strip || a.value.as_ref() == METADATA_NS.as_bytes())
+        {
+            continue;
+        }
+        let key = resolve_name(reader, a.key, true, opts.ns_mode);
+        let val = a.decode_and_unescape_value(reader.decoder())?;
+        let mut val = val.trim().to_string();
 
         if val.is_empty() {
             continue;
         }
 
+        let p = make_path(stack, opts.path_style, Some(&format!("@{key}")));
+        limit_text(&mut val, &p, opts);
         out.push(FlatEntry {
-            p: make_path(stack, opts.path_style, Some(&format!("@{key}"))),
+            p,
             k: ValueKind::Attr,
             v: val,
+            l: current_line(stack),
         });
     }
     Ok(())
 }
 
+/// Text of `&name;` / `&#n;`; unknown entities are kept as written.
+fn resolve_entity(e: &BytesRef<'_>) -> Result<String> {
+    if let Some(c) = e.resolve_char_ref()? {
+        return Ok(c.to_string());
+    }
+    let name = e.decode()?;
+    Ok(match resolve_predefined_entity(&name) {
+        Some(s) => s.to_string(),
+        None => format!("&{name};"),
+    })
+}
+
+/// Where a text run sits among its element's child elements.
+#[derive(Clone, Copy)]
+struct Edges {
+    /// A child element ends right before the run.
+    after_child: bool,
+    /// A child element starts right after the run.
+    before_child: bool,
+}
+
+/// Emit a joined text run. Runs that are only whitespace are layout between
+/// tags, except in a `preserve_space` element with no child elements (`leaf`).
+/// Other runs are trimmed, but in mixed content (`Hi <b>there</b> friend`)
+/// whitespace next to a child element is kept as one space so words stay apart.
+fn flush_text(
+    out: &mut Vec<FlatEntry>,
+    stack: &[Frame],
+    opts: &FlattenOptions,
+    run: TextRun,
+    leaf: bool,
+    edges: Edges,
+) {
+    if run.text.is_empty() || stack.is_empty() {
+        return;
+    }
+    let preserve = stack.last().is_some_and(|f| f.preserve);
+    let blank = !run.plain && !run.cdata;
+    if blank && !(preserve && leaf) {
+        return;
+    }
+
+    let mut text = if preserve {
+        run.text
+    } else {
+        let lead = edges.after_child && run.text.starts_with(char::is_whitespace);
+        let trail = edges.before_child && run.text.ends_with(char::is_whitespace);
+        format!(
+            "{}{}{}",
+            if lead { " " } else { "" },
+            run.text.trim(),
+            if trail { " " } else { "" }
+        )
+    };
+    // A value made only of CDATA sections round-trips as CDATA.
+    let leaf_name = if run.cdata && !run.plain {
+        "#cdata"
+    } else {
+        "#text"
+    };
+    let p = make_path(stack, opts.path_style, Some(leaf_name));
+    limit_text(&mut text, &p, opts);
+    out.push(FlatEntry {
+        p,
+        k: ValueKind::Text,
+        v: text,
+        // A whitespace-only value has no first character; use its element's line.
+        l: run.line.or_else(|| current_line(stack)),
+    });
+}
+
+/// Comments, processing instructions and doctypes are kept verbatim (never
+/// truncated) so they survive a round trip.
+fn push_markup(
+    out: &mut Vec<FlatEntry>,
+    stack: &[Frame],
+    opts: &FlattenOptions,
+    leaf: &str,
+    k: ValueKind,
+    text: String,
+    line: Option<usize>,
+) {
+    if text.is_empty() {
+        return;
+    }
+    out.push(FlatEntry {
+        p: make_path(stack, opts.path_style, Some(leaf)),
+        k,
+        v: text,
+        l: line,
+    });
+}
+
+/// Offset of the `>` closing the tag that was just read.
+fn tag_end(reader: &NsReader<&[u8]>) -> usize {
+    (reader.buffer_position() as usize).saturating_sub(1)
+}
+
+/// Bytes of leading whitespace, so a text's line is that of its first character.
+fn leading_space(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(bytes.len())
+}
+
+fn current_line(stack: &[Frame]) -> Option<usize> {
+    stack.last().and_then(|f| f.line)
+}
+
 fn make_path(stack: &[Frame], style: PathStyle, leaf: Option<&str>) -> String {
     let sep = match style {
         PathStyle::Slash => "/",
@@ -193,21 +502,24 @@ fn make_path(stack: &[Frame], style: PathStyle, leaf: Option<&str>) -> String {
     }
 
     if let Some(leaf) = leaf {
-        s.push_str(sep);
+        // Outside the root element (prolog comments) the leaf stands alone.
+        if !stack.is_empty() || style == PathStyle::Slash {
+            s.push_str(sep);
+        }
         s.push_str(leaf);
     }
 
     s
 }
 
-fn normalize_text(mut s: String, max_len: usize) -> String {
-    // trim already handled by quick-xml config, but keep safe
-    s = s.trim().to_string();
-    if s.is_empty() {
-        return s;
-    }
-    if max_len > 0 && s.len() > max_len {
-        s.truncate(max_len);
+/// Apply `max_text_len` (in chars, or tokens with `truncate_tokenizer`) unless
+/// the path matches a `never_truncate` pattern.
+fn limit_text(s: &mut String, path: &str, opts: &FlattenOptions) {
+    if opts.max_text_len == 0 || opts.never_truncate.iter().any(|p| p.matches(path)) {
+        return;
     }
-    s
+    match opts.truncate_tokenizer {
+        Some(tokenizer) => truncate_tokens(s, opts.max_text_len, tokenizer),
+        None => truncate_chars(s, opts.max_text_len),
+    };
 }
@@ -1,5 +1,12 @@
+mod error;
 mod flatten;
-mod options;
+pub mod options;
+mod position;
+pub mod tree;
+mod write;
 
-pub use flatten::{FlatEntry, flatten_xml_file};
-pub use options::FlattenOptions;
+pub use error::XmlSyntaxError;
+pub use flatten::{FlatEntry, ValueKind, flatten_xml_file, flatten_xml_str};
+pub use options::{FlattenOptions, NsMode, PathStyle};
+pub use tree::{build_document, build_tree};
+pub use write::write_document;
@@ -1,11 +1,181 @@
-use crate::cli::PathStyle;
+use crate::error::{Error, Result};
+use crate::filter::PathPattern;
+use crate::util::Tokenizer;
 
+/// How XML is turned into entries; see [`FlattenOptions::builder`].
 #[derive(Clone, Debug)]
 pub struct FlattenOptions {
     pub include_attributes: bool,
     pub include_text: bool,
     pub include_cdata: bool,
-    pub strip_namespace_prefix: bool,
+    pub ns_mode: NsMode,
+    /// Longest text value kept (0 = unlimited); longer values end in a
+    /// `…[+N chars]` marker.
     pub max_text_len: usize,
+    /// Count `max_text_len` in tokens of this tokenizer instead of chars.
+    pub truncate_tokenizer: Option<Tokenizer>,
+    /// Values under these paths are never truncated; set with
+    /// [`FlattenOptionsBuilder::never_truncate`].
+    pub(crate) never_truncate: Vec<PathPattern>,
     pub path_style: PathStyle,
+    pub with_locations: bool,
+    pub include_comments: bool,
+    /// Element names whose text (and their descendants' text) keeps its whitespace.
+    pub preserve_space: Vec<String>,
+}
+
+impl FlattenOptions {
+    /// Start from the command line's defaults.
+    pub fn builder() -> FlattenOptionsBuilder {
+        FlattenOptionsBuilder::default()
+    }
+}
+
+/// Builds [`FlattenOptions`]; every setting starts at the command line's default.
+///
+/// ```
+/// use sfmeta_reader::{FlattenOptions, NsMode};
+///
+/// let opts = FlattenOptions::builder()
+///     .include_attributes(true)
+///     .ns_mode(NsMode::Prefix)
+///     .max_text_len(0)
+///     .build()?;
+/// # Ok::<(), sfmeta_reader::Error>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct FlattenOptionsBuilder {
+    include_attributes: bool,
+    ns_mode: NsMode,
+    max_text_len: usize,
+    truncate_tokenizer: Option<Tokenizer>,
+    never_truncate: Vec<String>,
+    path_style: PathStyle,
+    with_locations: bool,
+    include_comments: bool,
+    preserve_space: Vec<String>,
+}
+
+impl Default for FlattenOptionsBuilder {
+    fn default() -> Self {
+        Self {
+            include_attributes: false,
+            ns_mode: NsMode::Strip,
+            max_text_len: 2048,
+            truncate_tokenizer: None,
+            never_truncate: vec!["errorConditionFormula".to_string()],
+            path_style: PathStyle::Slash,
+            with_locations: false,
+            include_comments: false,
+            preserve_space: Vec::new(),
+        }
+    }
+}
+
+impl FlattenOptionsBuilder {
+    /// Emit XML attributes as `@name` entries (default: off).
+    pub fn include_attributes(mut self, on: bool) -> Self {
+        self.include_attributes = on;
+        self
+    }
+
+    /// How namespaced names are written (default: [`NsMode::Strip`]).
+    pub fn ns_mode(mut self, mode: NsMode) -> Self {
+        self.ns_mode = mode;
+        self
+    }
+
+    /// Cut text values longer than `len` chars, or tokens with
+    /// [`truncate_by_tokens`](Self::truncate_by_tokens); 0 keeps everything
+    /// (default: 2048).
+    pub fn max_text_len(mut self, len: usize) -> Self {
+        self.max_text_len = len;
+        self
+    }
+
+    /// Count `max_text_len` in tokens of `tokenizer` instead of chars.
+    pub fn truncate_by_tokens(mut self, tokenizer: Tokenizer) -> Self {
+        self.truncate_tokenizer = Some(tokenizer);
+        self
+    }
+
+    /// Element names or path patterns whose values are never cut, replacing the
+    /// default `errorConditionFormula`.
+    pub fn never_truncate<I, S>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.never_truncate = patterns.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Path separator (default: [`PathStyle::Slash`]).
+    pub fn path_style(mut self, style: PathStyle) -> Self {
+        self.path_style = style;
+        self
+    }
+
+    /// Record each entry's source line in [`FlatEntry::l`](crate::FlatEntry::l).
+    pub fn with_locations(mut self, on: bool) -> Self {
+        self.with_locations = on;
+        self
+    }
+
+    /// Keep comments, processing instructions and the doctype as entries.
+    pub fn include_comments(mut self, on: bool) -> Self {
+        self.include_comments = on;
+        self
+    }
+
+    /// Element names whose text keeps its leading, trailing and blank-only whitespace.
+    pub fn preserve_space<I, S>(mut self, elements: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.preserve_space = elements.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Fails with [`Error::InvalidOption`] on a malformed `never_truncate` pattern.
+    pub fn build(self) -> Result<FlattenOptions> {
+        let never_truncate = self
+            .never_truncate
+            .iter()
+            .filter(|p| !p.is_empty())
+            .map(|p| {
+                PathPattern::element_or_pattern(p)
+                    .map_err(|err| Error::InvalidOption(format!("never_truncate: {err:#}")))
+            })
+            .collect::<Result<_>>()?;
+        Ok(FlattenOptions {
+            include_attributes: self.include_attributes,
+            include_text: true,
+            include_cdata: true,
+            ns_mode: self.ns_mode,
+            max_text_len: self.max_text_len,
+            truncate_tokenizer: self.truncate_tokenizer,
+            never_truncate,
+            path_style: self.path_style,
+            with_locations: self.with_locations,
+            include_comments: self.include_comments,
+            preserve_space: self.preserve_space,
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum NsMode {
+    Strip,
+    Prefix,
+    Clark,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum PathStyle {
+    Slash,
+    Dot,
 }
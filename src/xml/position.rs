@@ -0,0 +1,57 @@
+/// Resolve a byte offset into a 1-based (line, column) pair; columns count chars.
+pub fn line_col(xml: &str, offset: usize) -> (usize, usize) {
+    let offset = floor_char_boundary(xml, offset);
+    let before = &xml[..offset];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let column = before[line_start..].chars().count() + 1;
+    (line, column)
+}
+
+/// The full source line containing `offset`, without its line terminator.
+pub fn line_at(xml: &str, offset: usize) -> &str {
+    let offset = floor_char_boundary(xml, offset);
+    let start = xml[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let end = xml[offset..].find('\n').map_or(xml.len(), |i| offset + i);
+    xml[start..end].trim_end_matches('\r')
+}
+
+fn floor_char_boundary(xml: &str, offset: usize) -> usize {
+    let mut offset = offset.min(xml.len());
+    while !xml.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+/// Incremental line counter for offsets that only move forward, as they do while
+/// streaming events; avoids rescanning the document for every entry.
+#[derive(Debug)]
+pub struct LineTracker {
+    offset: usize,
+    line: usize,
+}
+
+impl LineTracker {
+    pub fn new() -> Self {
+        Self { offset: 0, line: 1 }
+    }
+
+    pub fn line_at(&mut self, xml: &str, offset: usize) -> usize {
+        let offset = offset.min(xml.len());
+        if offset > self.offset {
+            self.line += xml.as_bytes()[self.offset..offset]
+                .iter()
+                .filter(|&&b| b == b'\n')
+                .count();
+            self.offset = offset;
+        }
+        self.line
+    }
+}
+
+impl Default for LineTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,200 @@
+use crate::xml::FlatEntry;
+use anyhow::{Result, bail};
+
+/// An element rebuilt from flattened entries.
+///
+/// `idx` is the sibling index carried in the path (`fields[3]`), kept so rebuilt
+/// subtrees can be addressed with the same paths the flattener produced.
+#[derive(Debug, Clone, Default)]
+pub struct Node {
+    pub name: String,
+    pub idx: usize,
+    pub attrs: Vec<(String, String)>,
+    pub children: Vec<Child>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Child {
+    Element(Node),
+    Text(String),
+    CData(String),
+    Comment(String),
+    /// Processing instruction content (`target data`).
+    Pi(String),
+    DocType(String),
+}
+
+/// One step of a flattened path.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Segment {
+    Element {
+        name: String,
+        idx: usize,
+    },
+    Text,
+    CData,
+    Comment,
+    Pi,
+    DocType,
+    /// `#xmlns` or `#xmlns:p`, a prefix declaration from the document's prefix map.
+    Namespace(String),
+    Attr(String),
+}
+
+impl Node {
+    /// Concatenated text and CDATA of this element (not of its descendants).
+    pub fn text(&self) -> String {
+        self.children
+            .iter()
+            .filter_map(|c| match c {
+                Child::Text(s) | Child::CData(s) => Some(s.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Child elements, in document order.
+    pub fn elements(&self) -> impl Iterator<Item = &Node> {
+        self.children.iter().filter_map(|c| match c {
+            Child::Element(n) => Some(n),
+            _ => None,
+        })
+    }
+
+    /// Text of the first child element called `name`.
+    pub fn child_text(&self, name: &str) -> Option<String> {
+        self.elements().find(|n| n.name == name).map(Node::text)
+    }
+
+    fn child_mut(&mut self, name: &str, idx: usize) -> &mut Node {
+        // Entries arrive in document order, so the match is almost always near the end.
+        let pos = self
+            .children
+            .iter()
+            .rposition(|c| matches!(c, Child::Element(n) if n.name == name && n.idx == idx));
+        let pos = pos.unwrap_or_else(|| {
+            self.children.push(Child::Element(Node {
+                name: name.to_string(),
+                idx,
+                ..Node::default()
+            }));
+            self.children.len() - 1
+        });
+        match &mut self.children[pos] {
+            Child::Element(n) => n,
+            _ => unreachable!("rposition() only matches elements"),
+        }
+    }
+}
+
+/// Split `s` at `sep`, except inside `{...}` (Clark-notation namespace URIs).
+pub fn split_outside_braces(s: &str, sep: char) -> Vec<&str> {
+    let mut out = Vec::new();
+    let (mut depth, mut start) = (0usize, 0);
+    for (i, c) in s.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            c if c == sep && depth == 0 => {
+                out.push(&s[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    out.push(&s[start..]);
+    out
+}
+
+/// Raw segments (`fields[3]`, `#text`, `@attr`) of a slash (`/a[0]/b[1]`) or dot
+/// (`a[0].b[1]`) path.
+pub fn path_segments(path: &str) -> Vec<&str> {
+    match path.strip_prefix('/') {
+        Some(rest) => split_outside_braces(rest, '/'),
+        None => split_outside_braces(path, '.'),
+    }
+}
+
+/// Split a flattened path (slash or dot style) into its segments.
+pub fn parse_path(path: &str) -> Result<Vec<Segment>> {
+    let mut out = Vec::new();
+    for raw in path_segments(path) {
+        let seg = match raw {
+            _ if raw.starts_with("#xmlns") => Segment::Namespace(raw[1..].to_string()),
+            "#text" => Segment::Text,
+            "#cdata" => Segment::CData,
+            "#comment" => Segment::Comment,
+            "#pi" => Segment::Pi,
+            "#doctype" => Segment::DocType,
+            _ if raw.starts_with('@') => Segment::Attr(raw[1..].to_string()),
+            _ => {
+                let Some((name, rest)) = raw.rsplit_once('[') else {
+                    bail!("Path segment without index: `{raw}` in {path}");
+                };
+                let idx = rest
+                    .strip_suffix(']')
+                    .and_then(|i| i.parse().ok())
+                    .ok_or_else(|| anyhow::anyhow!("Bad index in `{raw}` in {path}"))?;
+                Segment::Element {
+                    name: name.to_string(),
+                    idx,
+                }
+            }
+        };
+        out.push(seg);
+    }
+    Ok(out)
+}
+
+/// Rebuild the element tree(s) described by `entries`, preserving entry order.
+pub fn build_tree(entries: &[FlatEntry]) -> Result<Vec<Node>> {
+    Ok(build_document(entries)?
+        .children
+        .into_iter()
+        .filter_map(|c| match c {
+            Child::Element(n) => Some(n),
+            _ => None,
+        })
+        .collect())
+}
+
+/// Like [`build_tree`], but returns the nameless document node so comments,
+/// processing instructions and the doctype outside the root element are kept.
+pub fn build_document(entries: &[FlatEntry]) -> Result<Node> {
+    let mut root = Node::default();
+
+    for e in entries {
+        let segs = parse_path(&e.p)?;
+        let Some((leaf, elems)) = segs.split_last() else {
+            continue;
+        };
+
+        let mut node = &mut root;
+        for seg in elems {
+            let Segment::Element { name, idx } = seg else {
+                bail!("Leaf segment in the middle of path: {}", e.p);
+            };
+            node = node.child_mut(name, *idx);
+        }
+
+        match leaf {
+            Segment::Element { name, idx } => {
+                // A bare element path (e.g. from a map) still creates the element.
+                let child = node.child_mut(name, *idx);
+                if !e.v.is_empty() {
+                    child.children.push(Child::Text(e.v.clone()));
+                }
+            }
+            Segment::Text => node.children.push(Child::Text(e.v.clone())),
+            Segment::CData => node.children.push(Child::CData(e.v.clone())),
+            Segment::Comment => node.children.push(Child::Comment(e.v.clone())),
+            Segment::Pi => node.children.push(Child::Pi(e.v.clone())),
+            Segment::DocType => node.children.push(Child::DocType(e.v.clone())),
+            Segment::Attr(k) => node.attrs.push((k.clone(), e.v.clone())),
+            // Declarations are kept on the document node and written on the root element.
+            Segment::Namespace(name) => node.attrs.push((name.clone(), e.v.clone())),
+        }
+    }
+
+    Ok(root)
+}
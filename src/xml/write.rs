@@ -0,0 +1,142 @@
+use crate::xml::tree::{Child, Node};
+use anyhow::Result;
+use quick_xml::Writer;
+use quick_xml::events::{BytesCData, BytesDecl, BytesEnd, BytesPI, BytesStart, BytesText, Event};
+
+/// Default namespace of every Metadata API document.
+pub const METADATA_NS: &str = "http://soap.sforce.com/2006/04/metadata";
+
+/// Well-known namespaces given their conventional prefix when written back from
+/// Clark notation; any other URI gets `ns1`, `ns2`, ...
+const KNOWN_PREFIXES: [(&str, &str); 1] = [("http://www.w3.org/2001/XMLSchema-instance", "xsi")];
+
+/// Serialize a rebuilt document (see [`build_document`]) as a Metadata API file
+/// (4-space indent, UTF-8 declaration), adding the metadata namespace to the root
+/// element if it is missing.
+///
+/// Prefix declarations carried on the document (`--ns-mode prefix`) are put back
+/// on the root element, and Clark names (`{uri}local`, `--ns-mode clark`) are
+/// rewritten to prefixed names declared there.
+///
+/// [`build_document`]: crate::xml::tree::build_document
+pub fn write_document(doc: &Node) -> Result<String> {
+    let mut w = Writer::new_with_indent(Vec::new(), b' ', 4);
+    w.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+
+    for c in &doc.children {
+        match c {
+            Child::Element(root) => {
+                let mut root = root.clone();
+                // Clark names take the prefixes the document declared, if any.
+                let mut prefixes: Vec<(String, String)> = doc
+                    .attrs
+                    .iter()
+                    .filter_map(|(k, uri)| {
+                        Some((uri.clone(), k.strip_prefix("xmlns:")?.to_string()))
+                    })
+                    .collect();
+                let declared = prefixes.len();
+                unclark(&mut root, &mut prefixes);
+
+                let mut decls: Vec<(String, String)> = doc.attrs.clone();
+                for (uri, prefix) in prefixes.into_iter().skip(declared) {
+                    decls.push((format!("xmlns:{prefix}"), uri));
+                }
+                if !decls.iter().chain(&root.attrs).any(|(k, _)| k == "xmlns") {
+                    decls.insert(0, ("xmlns".to_string(), METADATA_NS.to_string()));
+                }
+                decls.retain(|(k, _)| !root.attrs.iter().any(|(a, _)| a == k));
+                root.attrs.splice(0..0, decls);
+
+                write_node(&mut w, &root, start_tag(&root))?;
+            }
+            // Stray text outside the root is not well-formed; drop it.
+            Child::Text(_) | Child::CData(_) => {}
+            markup => write_markup(&mut w, markup)?,
+        }
+    }
+
+    let mut s = String::from_utf8(w.into_inner())?;
+    s.push('\n');
+    Ok(s)
+}
+
+/// Rewrite Clark names under `node` to prefixed ones, recording each new
+/// `(uri, prefix)` pair in `prefixes`.
+fn unclark(node: &mut Node, prefixes: &mut Vec<(String, String)>) {
+    node.name = prefixed(&node.name, prefixes);
+    for (k, _) in &mut node.attrs {
+        *k = prefixed(k, prefixes);
+    }
+    for c in &mut node.children {
+        if let Child::Element(n) = c {
+            unclark(n, prefixes);
+        }
+    }
+}
+
+fn prefixed(name: &str, prefixes: &mut Vec<(String, String)>) -> String {
+    let Some((uri, local)) = name.strip_prefix('{').and_then(|s| s.split_once('}')) else {
+        return name.to_string();
+    };
+    let prefix = match prefixes.iter().find(|(u, _)| u == uri) {
+        Some((_, p)) => p.clone(),
+        None => {
+            let taken = |p: &str| prefixes.iter().any(|(_, q)| q == p);
+            let p = KNOWN_PREFIXES
+                .iter()
+                .find(|(u, p)| *u == uri && !taken(p))
+                .map(|(_, p)| p.to_string())
+                .unwrap_or_else(|| {
+                    (1..)
+                        .map(|n| format!("ns{n}"))
+                        .find(|p| !taken(p))
+                        .expect("unbounded")
+                });
+            prefixes.push((uri.to_string(), p.clone()));
+            p
+        }
+    };
+    format!("{prefix}:{local}")
+}
+
+fn start_tag(node: &Node) -> BytesStart<'_> {
+    let mut start = BytesStart::new(node.name.as_str());
+    for (k, v) in &node.attrs {
+        start.push_attribute((k.as_str(), v.as_str()));
+    }
+    start
+}
+
+fn write_node(w: &mut Writer<Vec<u8>>, node: &Node, start: BytesStart<'_>) -> Result<()> {
+    if node.children.is_empty() {
+        w.write_event(Event::Empty(start))?;
+        return Ok(());
+    }
+
+    w.write_event(Event::Start(start))?;
+    for c in &node.children {
+        match c {
+            Child::Element(n) => write_node(w, n, start_tag(n))?,
+            Child::Text(t) => w.write_event(Event::Text(BytesText::new(t)))?,
+            Child::CData(t) => w.write_event(Event::CData(BytesCData::new(t.as_str())))?,
+            markup => write_markup(w, markup)?,
+        }
+    }
+    w.write_event(Event::End(BytesEnd::new(node.name.as_str())))?;
+    Ok(())
+}
+
+fn write_markup(w: &mut Writer<Vec<u8>>, child: &Child) -> Result<()> {
+    match child {
+        Child::Comment(t) => {
+            w.write_event(Event::Comment(BytesText::from_escaped(format!(" {t} "))))?
+        }
+        Child::Pi(t) => w.write_event(Event::PI(BytesPI::new(t.as_str())))?,
+        Child::DocType(t) => w.write_event(Event::DocType(BytesText::from_escaped(t.as_str())))?,
+        Child::Element(_) | Child::Text(_) | Child::CData(_) => {
+            unreachable!("only markup children are passed here")
+        }
+    }
+    Ok(())
+}
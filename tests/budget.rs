@@ -0,0 +1,177 @@
+#![cfg(feature = "cli")]
+
+mod common;
+
+use common::{run, scratch, stderr, stdout, write};
+use std::path::{Path, PathBuf};
+
+/// A profile with security-relevant sections and a bulky low-value one.
+fn profile() -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <Profile xmlns=\"http://soap.sforce.com/2006/04/metadata\">\n",
+    );
+    for i in 0..12 {
+        xml.push_str(&format!(
+            "    <layoutAssignments><layout>Account-Account Layout {i}</layout>\
+             <recordType>Account.Type{i}</recordType></layoutAssignments>\n"
+        ));
+    }
+    for name in ["ModifyAllData", "ViewAllData", "ManageUsers"] {
+        xml.push_str(&format!(
+            "    <userPermissions><enabled>true</enabled><name>{name}</name></userPermissions>\n"
+        ));
+    }
+    xml.push_str(
+        "    <classAccesses><apexClass>AdminTools</apexClass><enabled>true</enabled></classAccesses>\n\
+         </Profile>\n",
+    );
+    xml
+}
+
+fn setup(name: &str) -> PathBuf {
+    let dir = scratch(name);
+    write(&dir, "profiles/Admin.profile-meta.xml", &profile());
+    dir
+}
+
+fn reduce(dir: &Path, limit: usize) -> std::process::Output {
+    let limit = limit.to_string();
+    run(
+        dir,
+        &["--input", "profiles", "--no-cache", "--max-tokens", &limit],
+    )
+}
+
+/// Tokens after reduction, from the `Output reduced from X to Y tokens` notice.
+fn reduced_tokens(out: &std::process::Output) -> Option<usize> {
+    let err = stderr(out);
+    let line = err.lines().find(|l| l.starts_with("Output reduced from"))?;
+    line.split(" to ").nth(1)?.split(' ').next()?.parse().ok()
+}
+
+/// The highest limit (in steps of 10) at which `layoutAssignments` is collapsed.
+fn collapsing_limit(dir: &Path) -> usize {
+    (40..4000)
+        .rev()
+        .step_by(10)
+        .find(|&limit| stdout(&reduce(dir, limit)).contains("collapsed `layoutAssignments`"))
+        .expect("some limit collapses layoutAssignments")
+}
+
+#[test]
+fn collapses_low_value_sections_but_keeps_permissions() {
+    let dir = setup("budget-collapse");
+    let limit = collapsing_limit(&dir);
+    let out = reduce(&dir, limit);
+    let text = stdout(&out);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(
+        reduced_tokens(&out).is_some_and(|n| n <= limit),
+        "{}",
+        stderr(&out)
+    );
+    assert!(
+        !text.contains("omitted ") && !text.contains("cut profiles"),
+        "{text}"
+    );
+
+    for name in ["ModifyAllData", "ViewAllData", "ManageUsers", "AdminTools"] {
+        assert!(text.contains(name), "{name} missing at {limit}:\n{text}");
+    }
+    assert!(
+        text.contains(
+            "/Profile[0]/#comment = 12 layoutAssignments elements collapsed by the token budget"
+        ),
+        "{text}"
+    );
+}
+
+#[test]
+fn fails_when_the_limit_cannot_be_met() {
+    let dir = setup("budget-unmet");
+    let out = reduce(&dir, 1);
+    assert_eq!(out.status.code(), Some(1));
+    assert!(stdout(&out).is_empty());
+    assert!(
+        stderr(&out).contains("--max-tokens 1 cannot be met"),
+        "{}",
+        stderr(&out)
+    );
+}
+
+#[test]
+fn reduced_output_converts_back_but_not_in_place() {
+    let dir = setup("budget-to-xml");
+    let limit = collapsing_limit(&dir);
+    let out = run(
+        &dir,
+        &[
+            "--input",
+            "profiles",
+            "--no-cache",
+            "--always-wrap",
+            "--max-tokens",
+            &limit.to_string(),
+        ],
+    );
+    write(&dir, "reduced.toon", &stdout(&out));
+
+    let out = run(&dir, &["to-xml", "reduced.toon"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(
+        stdout(&out)
+            .contains("<!-- 12 layoutAssignments elements collapsed by the token budget -->"),
+        "{}",
+        stdout(&out)
+    );
+
+    let out = run(&dir, &["to-xml", "reduced.toon", "--in-place"]);
+    assert!(!out.status.success());
+    assert!(
+        stderr(&out).contains("reduced by --max-tokens"),
+        "{}",
+        stderr(&out)
+    );
+    let source = std::fs::read_to_string(dir.join("profiles/Admin.profile-meta.xml")).unwrap();
+    assert_eq!(source, profile());
+}
+
+#[test]
+fn wrapped_yaml_lists_the_entries_left_after_a_cut() {
+    let dir = setup("budget-yaml-cut");
+    let cut = (40..4000)
+        .rev()
+        .step_by(10)
+        .find_map(|limit| {
+            let out = run(
+                &dir,
+                &[
+                    "--input",
+                    "profiles",
+                    "--no-cache",
+                    "-f",
+                    "yaml",
+                    "--max-tokens",
+                    &limit.to_string(),
+                ],
+            );
+            let text = stdout(&out);
+            let kept: usize = text
+                .split(" after ")
+                .nth(1)?
+                .split(' ')
+                .next()?
+                .parse()
+                .ok()?;
+            (kept > 0).then_some((kept, text))
+        })
+        .expect("some limit cuts the file");
+
+    let (kept, text) = cut;
+    let entries = text
+        .lines()
+        .filter(|l| l.starts_with("  /Profile[0]/"))
+        .count();
+    assert_eq!(entries, kept, "{text}");
+}
@@ -0,0 +1,83 @@
+#![cfg(feature = "cli")]
+
+mod common;
+
+use common::{run, scratch, stderr, stdout, write};
+use std::path::Path;
+
+const FIELD: &str = "<CustomField><fullName>Rev__c</fullName><label>Revenue</label></CustomField>";
+
+/// Render `fields` with the cache in `cache`; returns stdout and the cache line.
+fn render(dir: &Path, args: &[&str]) -> (String, String) {
+    let mut all = vec!["--input", "fields", "--cache-dir", "cache"];
+    all.extend_from_slice(args);
+    let out = run(dir, &all);
+    assert!(out.status.success(), "{}", stderr(&out));
+    let line = stderr(&out)
+        .lines()
+        .find(|l| l.starts_with("Cache:"))
+        .unwrap_or_default()
+        .to_string();
+    (stdout(&out), line)
+}
+
+#[test]
+fn unchanged_files_are_read_back_from_the_cache() {
+    let dir = scratch("cache-hits");
+    write(&dir, "fields/Rev__c.field-meta.xml", FIELD);
+    write(&dir, "fields/Zed__c.field-meta.xml", FIELD);
+
+    let (first, line) = render(&dir, &[]);
+    assert!(
+        line.starts_with("Cache: 0 hit(s), 2 miss(es) in "),
+        "{line}"
+    );
+    assert_eq!(
+        std::fs::read_to_string(dir.join("cache/.gitignore")).unwrap(),
+        "*\n"
+    );
+
+    let (second, line) = render(&dir, &[]);
+    assert!(
+        line.starts_with("Cache: 2 hit(s), 0 miss(es) in "),
+        "{line}"
+    );
+    assert_eq!(second, first);
+}
+
+#[test]
+fn changed_contents_or_options_miss() {
+    let dir = scratch("cache-misses");
+    write(&dir, "fields/Rev__c.field-meta.xml", FIELD);
+    write(&dir, "fields/Zed__c.field-meta.xml", FIELD);
+    render(&dir, &[]);
+
+    write(
+        &dir,
+        "fields/Zed__c.field-meta.xml",
+        "<CustomField><fullName>Zed__c</fullName></CustomField>",
+    );
+    let (out, line) = render(&dir, &[]);
+    assert!(
+        line.starts_with("Cache: 1 hit(s), 1 miss(es) in "),
+        "{line}"
+    );
+    assert!(out.contains("Zed__c"), "{out}");
+
+    let (out, line) = render(&dir, &["-f", "json"]);
+    assert!(
+        line.starts_with("Cache: 0 hit(s), 2 miss(es) in "),
+        "{line}"
+    );
+    assert!(out.contains("\"Zed__c\""), "{out}");
+}
+
+#[test]
+fn no_cache_neither_reads_nor_writes() {
+    let dir = scratch("cache-off");
+    write(&dir, "fields/Rev__c.field-meta.xml", FIELD);
+    let out = run(&dir, &["--input", "fields", "--no-cache"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(!stderr(&out).contains("Cache:"), "{}", stderr(&out));
+    assert!(!dir.join(".sfmeta-cache").exists());
+}
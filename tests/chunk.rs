@@ -0,0 +1,58 @@
+#![cfg(feature = "cli")]
+
+mod common;
+
+use common::{run, scratch, stderr, write};
+use serde_json::Value;
+
+#[test]
+fn every_chunk_fits_the_limit() {
+    let dir = scratch("chunk-limit");
+    for i in 0..40 {
+        write(
+            &dir,
+            &format!("fields/F{i}__c.field-meta.xml"),
+            &format!(
+                "<CustomField><fullName>F{i}__c</fullName><label>Field {i}</label>\
+                 <type>Text</type><length>{}</length></CustomField>",
+                i * 7
+            ),
+        );
+    }
+
+    for format in ["toon", "compact", "json", "yaml", "ndjson", "markdown"] {
+        for limit in [50, 64, 120] {
+            let out_dir = format!("out-{format}-{limit}");
+            let limit_arg = limit.to_string();
+            let out = run(
+                &dir,
+                &[
+                    "--input",
+                    "fields",
+                    "--format",
+                    format,
+                    "--no-cache",
+                    "--chunk-tokens",
+                    &limit_arg,
+                    "--output-dir",
+                    &out_dir,
+                ],
+            );
+            assert!(out.status.success(), "{}", stderr(&out));
+
+            let manifest =
+                std::fs::read_to_string(dir.join(&out_dir).join("manifest.json")).unwrap();
+            let manifest: Value = serde_json::from_str(&manifest).unwrap();
+            let chunks = manifest["chunks"].as_array().unwrap();
+            assert!(chunks.len() > 1, "{format} at {limit}: one chunk");
+            for chunk in chunks {
+                let tokens = chunk["tokens"].as_u64().unwrap() as usize;
+                assert!(
+                    tokens <= limit,
+                    "{format}: {} has {tokens} tokens, limit {limit}",
+                    chunk["file"]
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,44 @@
+#![cfg(feature = "cli")]
+
+mod common;
+
+use common::{run, scratch, stderr, write};
+
+const FIELD: &str = "<CustomField><fullName>Rev__c</fullName></CustomField>";
+
+#[test]
+fn explicit_output_displaces_profile_output_dir() {
+    let dir = scratch("config-output");
+    write(&dir, "sfdx-project.json", "{}");
+    write(&dir, "fields/Rev__c.field-meta.xml", FIELD);
+    write(
+        &dir,
+        "sfmeta-reader.toml",
+        "no-cache = true\n\n[profile.mirror]\noutput-dir = \"mirror\"\nwatch = true\n",
+    );
+
+    let out = run(
+        &dir,
+        &["--input", "fields", "--profile", "mirror", "-o", "out.toon"],
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    let rendered = std::fs::read_to_string(dir.join("out.toon")).unwrap();
+    assert!(rendered.contains("Rev__c"), "{rendered}");
+    assert!(!dir.join("mirror").exists());
+}
+
+#[test]
+fn profile_output_dir_applies_without_explicit_output() {
+    let dir = scratch("config-output-dir");
+    write(&dir, "sfdx-project.json", "{}");
+    write(&dir, "fields/Rev__c.field-meta.xml", FIELD);
+    write(
+        &dir,
+        "sfmeta-reader.toml",
+        "no-cache = true\n\n[profile.mirror]\noutput-dir = \"mirror\"\n",
+    );
+
+    let out = run(&dir, &["--input", "fields", "--profile", "mirror"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(dir.join("mirror").is_dir());
+}
@@ -0,0 +1,70 @@
+#![cfg(feature = "cli")]
+
+mod common;
+
+use common::{run, scratch, stderr, stdout, write};
+use std::path::Path;
+
+const PROFILE: &str = r#"<Profile xmlns="http://soap.sforce.com/2006/04/metadata">
+    <objectPermissions><allowCreate>true</allowCreate><allowDelete>false</allowDelete><allowEdit>true</allowEdit><allowRead>true</allowRead><object>Account</object></objectPermissions>
+    <fieldPermissions><editable>false</editable><field>Account.Rating</field><readable>true</readable></fieldPermissions>
+</Profile>
+"#;
+
+const OBJECT: &str = r#"<CustomObject xmlns="http://soap.sforce.com/2006/04/metadata">
+    <fields><fullName>Rev__c</fullName><label>Revenue, net</label><required>true</required><type>Currency</type></fields>
+    <fields><fullName>Note__c</fullName><label>Say "hi"</label><length>255</length><type>Text</type></fields>
+</CustomObject>
+"#;
+
+fn csv(dir: &Path, input: &str, args: &[&str]) -> String {
+    let mut all = vec!["--input", input, "--no-cache", "-f", "csv"];
+    all.extend_from_slice(args);
+    let out = run(dir, &all);
+    assert!(out.status.success(), "{}", stderr(&out));
+    stdout(&out)
+}
+
+#[test]
+fn permissions_table_has_one_row_per_object_or_field() {
+    let dir = scratch("csv-permissions");
+    write(&dir, "profiles/Admin.profile-meta.xml", PROFILE);
+    write(&dir, "profiles/Sales.profile-meta.xml", PROFILE);
+    assert_eq!(
+        csv(&dir, "profiles", &[]),
+        "file,kind,name,object,field,read,edit,create,delete
+profiles/Admin.profile-meta.xml,Profile,Admin,Account,,true,true,true,false
+profiles/Admin.profile-meta.xml,Profile,Admin,Account,Rating,true,false,,
+profiles/Sales.profile-meta.xml,Profile,Sales,Account,,true,true,true,false
+profiles/Sales.profile-meta.xml,Profile,Sales,Account,Rating,true,false,,
+"
+    );
+}
+
+#[test]
+fn fields_table_quotes_values_that_need_it() {
+    let dir = scratch("csv-fields");
+    write(&dir, "Deal__c.object-meta.xml", OBJECT);
+    assert_eq!(
+        csv(&dir, "Deal__c.object-meta.xml", &["--csv-table", "fields"]),
+        r#"file,object,field,type,label,required,unique,length
+Deal__c.object-meta.xml,Deal__c,Rev__c,Currency,"Revenue, net",true,false,
+Deal__c.object-meta.xml,Deal__c,Note__c,Text,"Say ""hi""",false,false,255
+"#
+    );
+}
+
+#[test]
+fn other_metadata_types_give_no_rows() {
+    let dir = scratch("csv-empty");
+    write(&dir, "objects/Deal__c.object-meta.xml", OBJECT);
+    write(&dir, "objects/Zed.profile-meta.xml", PROFILE);
+    assert_eq!(csv(&dir, "objects/Deal__c.object-meta.xml", &[]), "");
+    assert_eq!(
+        csv(&dir, "objects", &[]),
+        "file,kind,name,object,field,read,edit,create,delete
+objects/Zed.profile-meta.xml,Profile,Zed,Account,,true,true,true,false
+objects/Zed.profile-meta.xml,Profile,Zed,Account,Rating,true,false,,
+"
+    );
+}
@@ -0,0 +1,159 @@
+#![cfg(feature = "cli")]
+
+mod common;
+
+use common::{run, scratch, stderr, write};
+use rusqlite::Connection;
+use rusqlite::types::ValueRef;
+use std::path::PathBuf;
+
+const PROFILE: &str = r#"<Profile xmlns="http://soap.sforce.com/2006/04/metadata">
+    <objectPermissions><allowCreate>false</allowCreate><allowDelete>false</allowDelete><allowEdit>true</allowEdit><allowRead>true</allowRead><object>Account</object></objectPermissions>
+    <fieldPermissions><editable>true</editable><field>Account.Rating</field><readable>true</readable></fieldPermissions>
+</Profile>
+"#;
+
+const OBJECT: &str = r#"<CustomObject xmlns="http://soap.sforce.com/2006/04/metadata">
+    <label>Deal</label>
+    <fields><fullName>Amount__c</fullName><label>Amount</label><required>true</required><type>Currency</type></fields>
+</CustomObject>
+"#;
+
+const FIELD: &str = r#"<CustomField xmlns="http://soap.sforce.com/2006/04/metadata">
+    <fullName>Stage__c</fullName><label>Stage</label><length>80</length><type>Text</type><unique>true</unique>
+</CustomField>
+"#;
+
+const FLOW: &str = r#"<Flow xmlns="http://soap.sforce.com/2006/04/metadata">
+    <label>Close Deal</label>
+    <processType>AutoLaunchedFlow</processType>
+    <status>Active</status>
+    <decisions><name>Is_Won</name><label>Is Won?</label><defaultConnector><targetReference>Notify</targetReference></defaultConnector></decisions>
+    <actionCalls><name>Notify</name><label>Notify Owner</label></actionCalls>
+</Flow>
+"#;
+
+fn export(name: &str) -> (PathBuf, Connection) {
+    let dir = scratch(name);
+    write(&dir, "app/profiles/Admin.profile-meta.xml", PROFILE);
+    write(&dir, "app/objects/Deal__c/Deal__c.object-meta.xml", OBJECT);
+    write(
+        &dir,
+        "app/objects/Deal__c/fields/Stage__c.field-meta.xml",
+        FIELD,
+    );
+    write(&dir, "app/flows/Close_Deal.flow-meta.xml", FLOW);
+    let out = run(&dir, &["export-sqlite", "app", "-o", "metadata.db"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    let db = Connection::open(dir.join("metadata.db")).unwrap();
+    (dir, db)
+}
+
+fn rows(db: &Connection, sql: &str) -> Vec<String> {
+    let mut stmt = db.prepare(sql).unwrap();
+    let n = stmt.column_count();
+    stmt.query_map([], |row| {
+        (0..n)
+            .map(|i| {
+                Ok(match row.get_ref(i)? {
+                    ValueRef::Null => "NULL".to_string(),
+                    ValueRef::Integer(v) => v.to_string(),
+                    ValueRef::Text(t) => String::from_utf8_lossy(t).into_owned(),
+                    other => format!("{other:?}"),
+                })
+            })
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map(|cols| cols.join("|"))
+    })
+    .unwrap()
+    .collect::<rusqlite::Result<_>>()
+    .unwrap()
+}
+
+#[test]
+fn components_and_permissions_are_exported() {
+    let (_dir, db) = export("sqlite-permissions");
+    assert_eq!(
+        rows(
+            &db,
+            "SELECT type, name, label FROM metadata_components ORDER BY type, name"
+        ),
+        [
+            "CustomField|Stage__c|Stage",
+            "CustomObject|Deal__c|Deal",
+            "Flow|Close_Deal|Close Deal",
+            "Profile|Admin|NULL",
+        ]
+    );
+    assert_eq!(
+        rows(
+            &db,
+            "SELECT c.name, p.object, p.allow_create, p.allow_read, p.allow_edit, p.allow_delete
+             FROM object_permissions p JOIN metadata_components c ON c.id = p.component_id"
+        ),
+        ["Admin|Account|0|1|1|0"]
+    );
+    assert_eq!(
+        rows(
+            &db,
+            "SELECT object, field, readable, editable FROM field_permissions"
+        ),
+        ["Account|Rating|1|1"]
+    );
+}
+
+#[test]
+fn fields_include_source_format_field_files() {
+    let (_dir, db) = export("sqlite-fields");
+    assert_eq!(
+        rows(
+            &db,
+            "SELECT object, name, type, label, is_required, is_unique, length FROM fields ORDER BY name"
+        ),
+        [
+            "Deal__c|Amount__c|Currency|Amount|1|0|NULL",
+            "Deal__c|Stage__c|Text|Stage|0|1|80",
+        ]
+    );
+}
+
+#[test]
+fn flows_list_elements_with_their_connector_target() {
+    let (_dir, db) = export("sqlite-flows");
+    assert_eq!(
+        rows(&db, "SELECT name, label, process_type, status FROM flows"),
+        ["Close_Deal|Close Deal|AutoLaunchedFlow|Active"]
+    );
+    assert_eq!(
+        rows(
+            &db,
+            "SELECT element_type, name, label, target FROM flow_elements ORDER BY name"
+        ),
+        [
+            "decisions|Is_Won|Is Won?|Notify",
+            "actionCalls|Notify|Notify Owner|NULL",
+        ]
+    );
+}
+
+#[test]
+fn flat_entries_keep_untruncated_text_and_reruns_replace_tables() {
+    let (dir, db) = export("sqlite-rerun");
+    let count = |db: &Connection| rows(db, "SELECT count(*) FROM flat_entries")[0].clone();
+    let before = count(&db);
+    assert_eq!(
+        rows(
+            &db,
+            "SELECT e.value, e.line FROM flat_entries e JOIN files f ON f.id = e.file_id
+             WHERE f.path LIKE '%Stage__c.field-meta.xml' AND e.path LIKE '%/label[0]/#text'"
+        ),
+        ["Stage|2"]
+    );
+    drop(db);
+
+    let out = run(&dir, &["export-sqlite", "app", "-o", "metadata.db"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    let db = Connection::open(dir.join("metadata.db")).unwrap();
+    assert_eq!(count(&db), before);
+    assert_eq!(rows(&db, "SELECT count(*) FROM files"), ["4"]);
+}
@@ -0,0 +1,106 @@
+#![cfg(feature = "cli")]
+
+mod common;
+
+use common::{run, scratch, stderr, stdout, write};
+
+const PROFILE: &str = r#"<Profile xmlns="http://soap.sforce.com/2006/04/metadata">
+    <custom>false</custom>
+    <fieldPermissions><editable>true</editable><field>Account.Rating</field></fieldPermissions>
+    <fieldPermissions><editable>false</editable><field>Contact.Email</field></fieldPermissions>
+    <userPermissions><enabled>true</enabled><name>ViewSetup</name></userPermissions>
+</Profile>
+"#;
+
+/// Paths and values of the TOON output for `PROFILE` under `args`.
+fn filtered(name: &str, args: &[&str]) -> String {
+    let dir = scratch(name);
+    write(&dir, "Admin.profile-meta.xml", PROFILE);
+    let mut all = vec!["--input", "Admin.profile-meta.xml", "--no-cache"];
+    all.extend_from_slice(args);
+    let out = run(&dir, &all);
+    assert!(out.status.success(), "{}", stderr(&out));
+    stdout(&out)
+}
+
+#[test]
+fn select_keeps_only_matching_paths() {
+    assert_eq!(
+        filtered(
+            "filter-select",
+            &["--select", "/Profile/fieldPermissions[*]/field"]
+        ),
+        "/Profile[0]/fieldPermissions[0]/field[0]/#text = Account.Rating\n\
+         /Profile[0]/fieldPermissions[1]/field[0]/#text = Contact.Email\n"
+    );
+    assert_eq!(
+        filtered("filter-select-depth", &["--select", "**/name"]),
+        "/Profile[0]/userPermissions[0]/name[0]/#text = ViewSetup\n"
+    );
+}
+
+#[test]
+fn drop_removes_matching_paths() {
+    assert_eq!(
+        filtered(
+            "filter-drop",
+            &[
+                "--drop",
+                "**/fieldPermissions",
+                "--drop",
+                "/Profile/userPermissions[0]/enabled"
+            ]
+        ),
+        "/Profile[0]/custom[0]/#text = false\n\
+         /Profile[0]/userPermissions[0]/name[0]/#text = ViewSetup\n"
+    );
+    assert!(
+        filtered("filter-drop-index", &["--drop", "**/fieldPermissions[1]"])
+            .contains("Account.Rating")
+    );
+}
+
+#[test]
+fn where_drops_whole_records_of_the_same_shape() {
+    assert_eq!(
+        filtered("filter-where-regex", &["--where", r"field~^Account\."]),
+        "/Profile[0]/custom[0]/#text = false\n\
+         /Profile[0]/fieldPermissions[0]/editable[0]/#text = true\n\
+         /Profile[0]/fieldPermissions[0]/field[0]/#text = Account.Rating\n\
+         /Profile[0]/userPermissions[0]/enabled[0]/#text = true\n\
+         /Profile[0]/userPermissions[0]/name[0]/#text = ViewSetup\n"
+    );
+
+    let out = filtered(
+        "filter-where-ne",
+        &["--where", "editable!=false", "--select", "**/field"],
+    );
+    assert_eq!(
+        out,
+        "/Profile[0]/fieldPermissions[0]/field[0]/#text = Account.Rating\n"
+    );
+
+    let out = filtered("filter-where-eq", &["--where", "name=ManageUsers"]);
+    assert!(!out.contains("userPermissions"), "{out}");
+    assert!(out.contains("fieldPermissions"), "{out}");
+}
+
+#[test]
+fn bad_filters_are_reported() {
+    let dir = scratch("filter-bad");
+    write(&dir, "Admin.profile-meta.xml", PROFILE);
+    for (args, message) in [
+        (["--where", "field~("], "Bad regex in `field~(`"),
+        (
+            ["--select", "/Profile/fieldPermissions[x]"],
+            "Bad index `x`",
+        ),
+    ] {
+        let out = run(
+            &dir,
+            &[&["--input", "Admin.profile-meta.xml"], &args[..]].concat(),
+        );
+        assert_eq!(out.status.code(), Some(1));
+        assert!(stderr(&out).contains(message), "{}", stderr(&out));
+    }
+}
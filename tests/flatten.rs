@@ -0,0 +1,136 @@
+use sfmeta_reader::{FlatEntry, FlattenOptions, NsMode, ValueKind, flatten_str, to_xml};
+
+fn flatten(xml: &str) -> Vec<FlatEntry> {
+    let opts = FlattenOptions::builder().max_text_len(0).build().unwrap();
+    flatten_str(xml, &opts).unwrap()
+}
+
+fn values(entries: &[FlatEntry]) -> Vec<(&str, &str)> {
+    entries
+        .iter()
+        .map(|e| (e.p.as_str(), e.v.as_str()))
+        .collect()
+}
+
+#[test]
+fn mixed_content_keeps_spaces_next_to_child_elements() {
+    let entries = flatten("<x>Hi <b>there</b> friend</x>");
+    assert_eq!(
+        values(&entries),
+        [
+            ("/x[0]/#text", "Hi "),
+            ("/x[0]/b[0]/#text", "there"),
+            ("/x[0]/#text", " friend"),
+        ]
+    );
+    let xml = to_xml(&entries).unwrap();
+    assert!(xml.contains("<x "), "{xml}");
+    assert!(xml.contains(">Hi <b>there</b> friend</x>"), "{xml}");
+}
+
+#[test]
+fn mixed_content_trims_layout_at_the_element_edges() {
+    let entries = flatten("<x>\n    Hi\n    <b>there</b>\n    friend\n</x>");
+    assert_eq!(
+        values(&entries),
+        [
+            ("/x[0]/#text", "Hi "),
+            ("/x[0]/b[0]/#text", "there"),
+            ("/x[0]/#text", " friend"),
+        ]
+    );
+}
+
+const DEFAULT_VALUE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<CustomField xmlns="http://soap.sforce.com/2006/04/metadata" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xmlns:xsd="http://www.w3.org/2001/XMLSchema">
+    <fullName>Rev__c</fullName>
+    <defaultValue xsi:type="xsd:string">0</defaultValue>
+</CustomField>
+"#;
+
+#[test]
+fn namespace_declarations_round_trip_in_prefix_and_clark_mode() {
+    for mode in [NsMode::Prefix, NsMode::Clark] {
+        let opts = FlattenOptions::builder()
+            .include_attributes(true)
+            .ns_mode(mode)
+            .build()
+            .unwrap();
+        let entries = flatten_str(DEFAULT_VALUE, &opts).unwrap();
+        assert!(
+            entries
+                .iter()
+                .any(|e| e.p == "/#xmlns:xsd" && matches!(e.k, ValueKind::Namespace)),
+            "{mode:?}: {entries:?}"
+        );
+        assert_eq!(to_xml(&entries).unwrap(), DEFAULT_VALUE, "{mode:?}");
+    }
+}
+
+#[test]
+fn clark_names_without_declarations_get_generated_prefixes() {
+    let opts = FlattenOptions::builder()
+        .include_attributes(true)
+        .ns_mode(NsMode::Clark)
+        .build()
+        .unwrap();
+    let entries: Vec<FlatEntry> = flatten_str(DEFAULT_VALUE, &opts)
+        .unwrap()
+        .into_iter()
+        .filter(|e| !matches!(e.k, ValueKind::Namespace))
+        .collect();
+    let xml = to_xml(&entries).unwrap();
+    assert!(
+        xml.contains(r#"xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance""#),
+        "{xml}"
+    );
+    assert!(
+        xml.contains(r#"<defaultValue xsi:type="xsd:string">"#),
+        "{xml}"
+    );
+}
+
+#[test]
+fn strip_mode_keeps_attribute_prefixes_and_declarations() {
+    let opts = FlattenOptions::builder()
+        .include_attributes(true)
+        .build()
+        .unwrap();
+    let xml = r#"<r xmlns="http://soap.sforce.com/2006/04/metadata" xmlns:a="urn:a"><a:e a:type="1" type="2"/></r>"#;
+    let entries = flatten_str(xml, &opts).unwrap();
+    assert_eq!(
+        values(&entries),
+        [
+            ("/r[0]/@xmlns:a", "urn:a"),
+            ("/r[0]/e[0]/@a:type", "1"),
+            ("/r[0]/e[0]/@type", "2"),
+        ]
+    );
+
+    let entries = flatten_str(DEFAULT_VALUE, &opts).unwrap();
+    assert_eq!(to_xml(&entries).unwrap(), DEFAULT_VALUE);
+}
+
+#[test]
+fn locations_give_each_value_the_line_it_starts_on() {
+    let opts = FlattenOptions::builder()
+        .max_text_len(0)
+        .include_attributes(true)
+        .with_locations(true)
+        .build()
+        .unwrap();
+    let xml = "<Flow a=\"1\"><description>\n    Long\n    text</description>\n\
+               <label><![CDATA[\n\nBig]]></label>\n\
+               <name>x</name></Flow>";
+    let entries = flatten_str(xml, &opts).unwrap();
+    let lines: Vec<(&str, Option<usize>)> = entries.iter().map(|e| (e.p.as_str(), e.l)).collect();
+    assert_eq!(
+        lines,
+        [
+            ("/Flow[0]/@a", Some(1)),
+            ("/Flow[0]/description[0]/#text", Some(2)),
+            ("/Flow[0]/label[0]/#cdata", Some(6)),
+            ("/Flow[0]/name[0]/#text", Some(7)),
+        ]
+    );
+}
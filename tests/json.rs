@@ -0,0 +1,183 @@
+#![cfg(feature = "cli")]
+
+mod common;
+
+use common::{run, scratch, stderr, stdout, write};
+use serde_json::{Value, json};
+
+const FIELD: &str = r#"<CustomField xmlns="http://soap.sforce.com/2006/04/metadata">
+    <fullName>1234</fullName>
+    <externalId>false</externalId>
+    <precision>18</precision>
+    <scale>2.5</scale>
+    <label>007</label>
+    <inlineHelpText>1e5</inlineHelpText>
+    <valueSet><value><fullName>A</fullName></value><value><fullName>B</fullName></value></valueSet>
+</CustomField>
+"#;
+
+/// Render `FIELD` as JSON with `args` and parse the result.
+fn json(name: &str, args: &[&str]) -> Value {
+    let dir = scratch(name);
+    write(&dir, "Rev__c.field-meta.xml", FIELD);
+    let mut all = vec![
+        "--input",
+        "Rev__c.field-meta.xml",
+        "--no-cache",
+        "--format",
+        "json",
+    ];
+    all.extend_from_slice(args);
+    let out = run(&dir, &all);
+    assert!(out.status.success(), "{}", stderr(&out));
+    serde_json::from_str(&stdout(&out)).unwrap()
+}
+
+#[test]
+fn typed_values_keep_text_that_would_not_read_back() {
+    let map = json("json-typed", &["--typed", "--json-mode", "map"]);
+    assert_eq!(
+        map,
+        json!({
+            "/CustomField[0]/fullName[0]/#text": "1234",
+            "/CustomField[0]/externalId[0]/#text": false,
+            "/CustomField[0]/precision[0]/#text": 18,
+            "/CustomField[0]/scale[0]/#text": 2.5,
+            "/CustomField[0]/label[0]/#text": "007",
+            "/CustomField[0]/inlineHelpText[0]/#text": "1e5",
+            "/CustomField[0]/valueSet[0]/value[0]/fullName[0]/#text": "A",
+            "/CustomField[0]/valueSet[0]/value[1]/fullName[0]/#text": "B",
+        })
+    );
+
+    let list = json("json-typed-list", &["--typed"]);
+    assert_eq!(
+        list[1],
+        json!({"k": "Text", "p": "/CustomField[0]/externalId[0]/#text", "v": false})
+    );
+}
+
+#[test]
+fn values_are_strings_without_typed() {
+    let map = json("json-untyped", &["--json-mode", "map"]);
+    assert_eq!(map["/CustomField[0]/precision[0]/#text"], "18");
+    assert_eq!(map["/CustomField[0]/externalId[0]/#text"], "false");
+}
+
+#[test]
+fn keep_string_overrides_typed_values() {
+    let map = json(
+        "json-keep-string",
+        &[
+            "--typed",
+            "--json-mode",
+            "map",
+            "--keep-string",
+            "precision",
+        ],
+    );
+    assert_eq!(map["/CustomField[0]/precision[0]/#text"], "18");
+    assert_eq!(map["/CustomField[0]/scale[0]/#text"], 2.5);
+    // A custom list replaces the default, so `fullName` is typed too.
+    assert_eq!(map["/CustomField[0]/fullName[0]/#text"], 1234);
+}
+
+#[test]
+fn typed_output_converts_back_to_the_same_text() {
+    let dir = scratch("json-typed-to-xml");
+    write(&dir, "Rev__c.field-meta.xml", FIELD);
+    let out = run(
+        &dir,
+        &[
+            "--input",
+            "Rev__c.field-meta.xml",
+            "--no-cache",
+            "-f",
+            "json",
+            "--typed",
+            "-o",
+            "typed.json",
+        ],
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    let out = run(&dir, &["to-xml", "typed.json"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    let xml = stdout(&out);
+    for text in [
+        "<precision>18</precision>",
+        "<scale>2.5</scale>",
+        "<externalId>false</externalId>",
+    ] {
+        assert!(xml.contains(text), "{xml}");
+    }
+}
+
+#[test]
+fn tree_mode_nests_repeated_elements_as_arrays() {
+    let tree = json("json-tree", &["--json-mode", "tree", "--typed"]);
+    assert_eq!(
+        tree,
+        json!({
+            "CustomField": {
+                "fullName": "1234",
+                "externalId": false,
+                "precision": 18,
+                "scale": 2.5,
+                "label": "007",
+                "inlineHelpText": "1e5",
+                "valueSet": {"value": [{"fullName": "A"}, {"fullName": "B"}]},
+            }
+        })
+    );
+}
+
+#[test]
+fn tree_mode_keeps_attributes_and_mixed_text() {
+    let dir = scratch("json-tree-attrs");
+    write(&dir, "m.xml", r#"<r a="1"><x>t<b>u</b>v</x></r>"#);
+    let out = run(
+        &dir,
+        &[
+            "--input",
+            "m.xml",
+            "--no-cache",
+            "-f",
+            "json",
+            "--json-mode",
+            "tree",
+            "--include-attributes",
+        ],
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    let tree: Value = serde_json::from_str(&stdout(&out)).unwrap();
+    assert_eq!(
+        tree,
+        json!({"r": {"@a": "1", "x": {"#text": "tv", "b": "u"}}})
+    );
+}
+
+#[test]
+fn wrapped_output_builds_each_file_from_its_entries() {
+    let dir = scratch("json-wrap");
+    write(&dir, "fields/Rev__c.field-meta.xml", FIELD);
+    write(&dir, "fields/Bad__c.field-meta.xml", "<bad");
+    let out = run(
+        &dir,
+        &[
+            "--input",
+            "fields",
+            "--no-cache",
+            "-f",
+            "json",
+            "--json-mode",
+            "tree",
+            "--typed",
+            "--keep-going",
+        ],
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    let v: Value = serde_json::from_str(&stdout(&out)).unwrap();
+    assert_eq!(v["files"][0]["file"], "fields/Rev__c.field-meta.xml");
+    assert_eq!(v["files"][0]["data"]["CustomField"]["precision"], 18);
+    assert_eq!(v["errors"][0]["file"], "fields/Bad__c.field-meta.xml");
+}
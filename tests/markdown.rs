@@ -0,0 +1,65 @@
+use sfmeta_reader::{FlattenOptions, flatten_str, render_markdown};
+
+fn markdown(file: &str, xml: &str) -> String {
+    let opts = FlattenOptions::builder().max_text_len(0).build().unwrap();
+    render_markdown(file, &flatten_str(xml, &opts).unwrap()).unwrap()
+}
+
+#[test]
+fn permissions_become_a_table_with_the_name_first() {
+    let md = markdown(
+        "Admin.profile-meta.xml",
+        "<Profile><custom>false</custom>\
+         <fieldPermissions><editable>true</editable><field>Account.Rating</field><readable>true</readable></fieldPermissions>\
+         <fieldPermissions><editable>false</editable><field>Contact.Email</field><readable>true</readable></fieldPermissions>\
+         </Profile>",
+    );
+    assert_eq!(
+        md,
+        "# Admin.profile-meta.xml (Profile)
+
+- **custom**: false
+
+## fieldPermissions
+
+| field | editable | readable |
+|---|---|---|
+| Account.Rating | ✓ | ✓ |
+| Contact.Email |  | ✓ |
+"
+    );
+}
+
+#[test]
+fn validation_rules_keep_their_formula_in_a_code_block() {
+    let md = markdown(
+        "Deal__c.object-meta.xml",
+        "<CustomObject><validationRules><fullName>Amount_Required</fullName><active>true</active>\
+         <errorConditionFormula>ISBLANK(Amount__c)</errorConditionFormula>\
+         <errorMessage>Enter an amount</errorMessage></validationRules></CustomObject>",
+    );
+    assert!(
+        md.contains(
+            "### Amount\\_Required\n\n- **active**: true\n- **errorMessage**: Enter an amount\n\n```\nISBLANK(Amount__c)\n```\n"
+        ),
+        "{md}"
+    );
+}
+
+#[test]
+fn markdown_syntax_in_values_is_escaped() {
+    let md = markdown(
+        "Rev__c.field-meta.xml",
+        "<CustomObject><description>Use *only* `Rev__c` | [docs]</description>\
+         <fields><fullName>Rev__c</fullName><label>A|B</label></fields></CustomObject>",
+    );
+    assert!(
+        md.starts_with("# Rev\\_\\_c.field-meta.xml (CustomObject)\n"),
+        "{md}"
+    );
+    assert!(
+        md.contains(r"- **description**: Use \*only\* \`Rev\_\_c\` \| \[docs\]"),
+        "{md}"
+    );
+    assert!(md.contains(r"| Rev\_\_c | A\|B |"), "{md}");
+}
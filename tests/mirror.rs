@@ -0,0 +1,111 @@
+#![cfg(feature = "cli")]
+
+mod common;
+
+use common::{run, scratch, stderr, write};
+use std::path::Path;
+
+const FIELD: &str = "<CustomField><fullName>Rev__c</fullName></CustomField>";
+
+/// Every file under `dir`, relative to it, sorted.
+fn files(dir: &Path) -> Vec<String> {
+    let mut out: Vec<String> = walkdir::WalkDir::new(dir)
+        .into_iter()
+        .map(|e| e.unwrap())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| {
+            let rel = e.path().strip_prefix(dir).unwrap();
+            rel.to_string_lossy().replace('\\', "/")
+        })
+        .collect();
+    out.sort();
+    out
+}
+
+#[test]
+fn mirror_keeps_the_tree_and_swaps_the_extension() {
+    let dir = scratch("mirror-tree");
+    write(
+        &dir,
+        "app/objects/Account/Account.object-meta.xml",
+        "<CustomObject><label>Account</label></CustomObject>",
+    );
+    write(
+        &dir,
+        "app/objects/Account/fields/Rev__c.field-meta.xml",
+        FIELD,
+    );
+    write(
+        &dir,
+        "app/package.xml",
+        "<Package><version>62.0</version></Package>",
+    );
+    let out = run(
+        &dir,
+        &["--input", "app", "--no-cache", "--output-dir", "out"],
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(
+        stderr(&out).contains("Wrote 3 file(s) to out"),
+        "{}",
+        stderr(&out)
+    );
+    assert_eq!(
+        files(&dir.join("out")),
+        [
+            "objects/Account/Account.object.toon",
+            "objects/Account/fields/Rev__c.field.toon",
+            "package.toon",
+        ]
+    );
+}
+
+#[test]
+fn single_file_input_lands_at_the_top_of_the_mirror() {
+    let dir = scratch("mirror-single");
+    write(&dir, "fields/Rev__c.field-meta.xml", FIELD);
+    let out = run(
+        &dir,
+        &[
+            "--input",
+            "fields/Rev__c.field-meta.xml",
+            "--no-cache",
+            "-f",
+            "json",
+            "--output-dir",
+            "out",
+        ],
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_eq!(files(&dir.join("out")), ["Rev__c.field.json"]);
+    let body = std::fs::read_to_string(dir.join("out/Rev__c.field.json")).unwrap();
+    assert!(body.contains("Rev__c"), "{body}");
+}
+
+#[test]
+fn each_format_uses_its_own_extension() {
+    let dir = scratch("mirror-ext");
+    write(&dir, "fields/Rev__c.field-meta.xml", FIELD);
+    for (format, ext) in [
+        ("compact", "txt"),
+        ("yaml", "yaml"),
+        ("ndjson", "ndjson"),
+        ("markdown", "md"),
+    ] {
+        let out_dir = format!("out-{format}");
+        let out = run(
+            &dir,
+            &[
+                "--input",
+                "fields",
+                "--no-cache",
+                "-f",
+                format,
+                "--output-dir",
+                &out_dir,
+            ],
+        );
+        assert!(out.status.success(), "{}", stderr(&out));
+        assert_eq!(files(&dir.join(&out_dir)), [format!("Rev__c.field.{ext}")]);
+    }
+}
@@ -0,0 +1,96 @@
+#![cfg(feature = "cli")]
+
+mod common;
+
+use common::{run, scratch, stderr, stdout, write};
+use serde_json::{Value, json};
+use std::path::{Path, PathBuf};
+
+const FIELD: &str =
+    "<CustomField><fullName>Rev__c</fullName><precision>18</precision></CustomField>";
+
+fn setup(name: &str) -> PathBuf {
+    let dir = scratch(name);
+    write(&dir, "fields/Rev__c.field-meta.xml", FIELD);
+    write(&dir, "fields/Zed__c.field-meta.xml", "<bad");
+    dir
+}
+
+fn lines(dir: &Path, args: &[&str]) -> Vec<Value> {
+    let mut all = vec![
+        "--input",
+        "fields",
+        "--no-cache",
+        "-f",
+        "ndjson",
+        "--keep-going",
+    ];
+    all.extend_from_slice(args);
+    let out = run(dir, &all);
+    assert!(out.status.success(), "{}", stderr(&out));
+    stdout(&out)
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect()
+}
+
+#[test]
+fn entry_mode_writes_one_line_per_entry_then_errors() {
+    let dir = setup("ndjson-entry");
+    let lines = lines(&dir, &["--typed"]);
+    assert_eq!(lines.len(), 3);
+    assert_eq!(
+        lines[1],
+        json!({
+            "file": "fields/Rev__c.field-meta.xml",
+            "type": "CustomField",
+            "p": "/CustomField[0]/precision[0]/#text",
+            "k": "Text",
+            "v": 18,
+        })
+    );
+    assert_eq!(
+        lines[2]["errors"][0]["file"],
+        "fields/Zed__c.field-meta.xml"
+    );
+}
+
+#[test]
+fn file_mode_writes_one_line_per_file() {
+    let dir = setup("ndjson-file");
+    let lines = lines(&dir, &["--ndjson-mode", "file"]);
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["type"], "CustomField");
+    assert_eq!(
+        lines[0]["entries"],
+        json!([
+            {"p": "/CustomField[0]/fullName[0]/#text", "k": "Text", "v": "Rev__c"},
+            {"p": "/CustomField[0]/precision[0]/#text", "k": "Text", "v": "18"},
+        ])
+    );
+}
+
+#[test]
+fn output_converts_back_to_xml() {
+    let dir = setup("ndjson-to-xml");
+    let out = run(
+        &dir,
+        &[
+            "--input",
+            "fields/Rev__c.field-meta.xml",
+            "--no-cache",
+            "-f",
+            "ndjson",
+            "-o",
+            "rev.ndjson",
+        ],
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    let out = run(&dir, &["to-xml", "rev.ndjson"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(
+        stdout(&out).contains("<fullName>Rev__c</fullName>\n    <precision>18</precision>"),
+        "{}",
+        stdout(&out)
+    );
+}
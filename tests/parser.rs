@@ -0,0 +1,26 @@
+use sfmeta_reader::parse_metadata;
+
+#[test]
+fn permission_block_without_name_does_not_swallow_the_next_one() {
+    let xml = r#"<PermissionSet xmlns="http://soap.sforce.com/2006/04/metadata">
+    <objectPermissions>
+        <allowRead>true</allowRead>
+    </objectPermissions>
+    <fieldPermissions>
+        <field>Account.Rev__c</field>
+        <readable>true</readable>
+        <editable>false</editable>
+    </fieldPermissions>
+    <objectPermissions>
+        <object>Account</object>
+        <allowRead>true</allowRead>
+    </objectPermissions>
+</PermissionSet>"#;
+
+    let ps = parse_metadata(xml, false).unwrap().permission_set.unwrap();
+    assert_eq!(ps.field_permissions.len(), 1);
+    assert_eq!(ps.field_permissions[0].field, "Account.Rev__c");
+    assert!(ps.field_permissions[0].readable);
+    assert_eq!(ps.object_permissions.len(), 1);
+    assert_eq!(ps.object_permissions[0].object, "Account");
+}
@@ -0,0 +1,175 @@
+#![cfg(feature = "cli")]
+
+mod common;
+
+use common::{run, scratch, stderr, stdout, write};
+
+const PROFILE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Profile xmlns="http://soap.sforce.com/2006/04/metadata" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
+    <custom>false</custom>
+</Profile>
+"#;
+
+fn assert_rejected(name: &str, op: &str) {
+    let dir = scratch(name);
+    let file = write(&dir, "Admin.profile-meta.xml", PROFILE);
+
+    let out = run(&dir, &["patch", "Admin.profile-meta.xml", "-e", op]);
+    assert_eq!(out.status.code(), Some(1), "{}", stderr(&out));
+    assert!(
+        stderr(&out).contains("Namespace declarations cannot be patched"),
+        "{}",
+        stderr(&out)
+    );
+    assert_eq!(std::fs::read_to_string(file).unwrap(), PROFILE);
+}
+
+#[test]
+fn delete_namespace_declaration_is_rejected() {
+    assert_rejected("patch-delete-xmlns", "delete /Profile[0]/#xmlns:xsi");
+}
+
+#[test]
+fn set_namespace_declaration_is_rejected() {
+    assert_rejected("patch-set-xmlns", "set /Profile[0]/#xmlns = x");
+}
+
+const PERMISSIONS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Profile xmlns="http://soap.sforce.com/2006/04/metadata">
+    <!-- keep me -->
+    <custom>false</custom>
+    <userPermissions>
+        <enabled>true</enabled>
+        <name>ViewSetup</name>
+    </userPermissions>
+    <userPermissions>
+        <enabled>false</enabled>
+        <name>ManageUsers</name>
+    </userPermissions>
+</Profile>
+"#;
+
+fn patch(name: &str, args: &[&str]) -> (std::process::Output, String) {
+    let dir = scratch(name);
+    let file = write(&dir, "Admin.profile-meta.xml", PERMISSIONS);
+    let mut all = vec!["patch", "Admin.profile-meta.xml"];
+    all.extend_from_slice(args);
+    let out = run(&dir, &all);
+    (out, std::fs::read_to_string(file).unwrap())
+}
+
+#[test]
+fn set_insert_and_delete_touch_only_their_elements() {
+    let (out, xml) = patch(
+        "patch-ops",
+        &[
+            "-e",
+            "set /Profile[0]/userPermissions[1]/enabled[0]/#text = true",
+            "-e",
+            "insert /Profile[0]/custom[0]/#text = true",
+            "-e",
+            "delete /Profile[0]/userPermissions[0]",
+        ],
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_eq!(
+        xml,
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<Profile xmlns="http://soap.sforce.com/2006/04/metadata">
+    <!-- keep me -->
+    <custom>true</custom>
+    <custom>false</custom>
+    <userPermissions>
+        <enabled>true</enabled>
+        <name>ManageUsers</name>
+    </userPermissions>
+</Profile>
+"#
+    );
+}
+
+#[test]
+fn set_creates_missing_children() {
+    let (out, xml) = patch(
+        "patch-create",
+        &["-e", "set /Profile[0]/userLicense[0]/#text = Salesforce"],
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(
+        xml.contains(
+            "    </userPermissions>\n    <userLicense>Salesforce</userLicense>\n</Profile>"
+        ),
+        "{xml}"
+    );
+}
+
+#[test]
+fn dry_run_prints_without_writing() {
+    let (out, xml) = patch(
+        "patch-dry-run",
+        &["--dry-run", "-e", "set /Profile[0]/custom[0]/#text = true"],
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(stdout(&out).contains("<custom>true</custom>"));
+    assert_eq!(xml, PERMISSIONS);
+}
+
+#[test]
+fn failed_op_leaves_the_file_alone() {
+    let (out, xml) = patch(
+        "patch-failed",
+        &[
+            "-e",
+            "set /Profile[0]/custom[0]/#text = true",
+            "-e",
+            "delete /Profile[0]/loginHours[0]",
+        ],
+    );
+    assert_eq!(out.status.code(), Some(1));
+    assert!(
+        stderr(&out).contains("Nothing to delete"),
+        "{}",
+        stderr(&out)
+    );
+    assert_eq!(xml, PERMISSIONS);
+}
+
+#[test]
+fn set_rejects_an_index_past_the_next_free_one() {
+    let (out, xml) = patch(
+        "patch-out-of-range",
+        &["-e", "set /Profile[0]/userPermissions[5]/name[0]/#text = X"],
+    );
+    assert_eq!(out.status.code(), Some(1));
+    assert!(
+        stderr(&out).contains("has 2 <userPermissions> children, so only [2] can be added"),
+        "{}",
+        stderr(&out)
+    );
+    assert_eq!(xml, PERMISSIONS);
+
+    let (out, xml) = patch(
+        "patch-next-index",
+        &["-e", "set /Profile[0]/userPermissions[2]/name[0]/#text = X"],
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(xml.contains("<name>X</name>"), "{xml}");
+}
+
+#[test]
+fn cdata_values_containing_the_terminator_are_split() {
+    let (out, xml) = patch(
+        "patch-cdata",
+        &["-e", "set /Profile[0]/description[0]/#cdata = a]]>b"],
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(
+        xml.contains("<description><![CDATA[a]]]]><![CDATA[>b]]></description>"),
+        "{xml}"
+    );
+
+    let dir = scratch("patch-cdata-read");
+    write(&dir, "Admin.profile-meta.xml", &xml);
+    let out = run(&dir, &["--input", "Admin.profile-meta.xml", "--no-cache"]);
+    assert!(stdout(&out).contains("a]]>b"), "{}", stdout(&out));
+}
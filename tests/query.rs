@@ -0,0 +1,120 @@
+#![cfg(feature = "cli")]
+
+mod common;
+
+use common::{run, scratch, stderr, stdout, write};
+
+const OBJECT: &str = r#"<CustomObject xmlns="http://soap.sforce.com/2006/04/metadata">
+    <fields><fullName>Acct__c</fullName><referenceTo>Account</referenceTo><type>Lookup</type></fields>
+    <fields><fullName>Rev__c</fullName><type>Currency</type></fields>
+    <fields><fullName>Opp__c</fullName><referenceTo>Opportunity</referenceTo><type>Lookup</type></fields>
+    <listViews name="all"><fullName>All</fullName></listViews>
+</CustomObject>
+"#;
+
+fn query(name: &str, expr: &str) -> std::process::Output {
+    let dir = scratch(name);
+    write(&dir, "objects/Deal__c.object-meta.xml", OBJECT);
+    run(&dir, &["query", expr, "objects"])
+}
+
+fn matched(name: &str, expr: &str) -> String {
+    let out = query(name, expr);
+    assert!(out.status.success(), "{}", stderr(&out));
+    stdout(&out)
+}
+
+#[test]
+fn child_filters_select_matching_subtrees() {
+    assert_eq!(
+        matched("query-filter", r#"//fields[type="Lookup"]/referenceTo"#),
+        "/CustomObject[0]/fields[0]/referenceTo[0]/#text = Account\n\
+         /CustomObject[0]/fields[2]/referenceTo[0]/#text = Opportunity\n"
+    );
+    assert_eq!(
+        matched("query-exists", "fields[referenceTo]/fullName"),
+        "/CustomObject[0]/fields[0]/fullName[0]/#text = Acct__c\n\
+         /CustomObject[0]/fields[2]/fullName[0]/#text = Opp__c\n"
+    );
+}
+
+#[test]
+fn positions_are_one_based_per_parent() {
+    assert_eq!(
+        matched("query-position", "/CustomObject/fields[2]/fullName/text()"),
+        "/CustomObject[0]/fields[1]/fullName[0]/#text = Rev__c\n"
+    );
+    assert_eq!(
+        matched("query-wildcard", "/CustomObject/*[4]"),
+        "/CustomObject[0]/listViews[0]/fullName[0]/#text = All\n"
+    );
+}
+
+#[test]
+fn final_attribute_step_selects_the_value() {
+    assert_eq!(
+        matched("query-attr", "//listViews/@name"),
+        "/CustomObject[0]/listViews[0]/@name = all\n"
+    );
+}
+
+#[test]
+fn no_match_is_not_an_error() {
+    let out = query("query-none", "//nothing");
+    assert!(out.status.success());
+    assert!(stdout(&out).is_empty());
+    assert!(stderr(&out).contains("No matches for //nothing"));
+}
+
+#[test]
+fn malformed_queries_are_rejected() {
+    for (expr, message) in [
+        ("/CustomObject/fields[0]", "Positions are 1-based"),
+        ("//@name/x", "must be the last step"),
+        (
+            "fields[type=Lookup]",
+            "Expected a quoted value at offset 12",
+        ),
+        ("fields[type", "Expected `]`"),
+    ] {
+        let out = query("query-malformed", expr);
+        assert_eq!(out.status.code(), Some(1), "{expr}");
+        assert!(stderr(&out).contains(message), "{expr}: {}", stderr(&out));
+    }
+}
+
+#[test]
+fn attributes_are_printed_only_when_selected_or_asked_for() {
+    let dir = scratch("query-attr-output");
+    write(
+        &dir,
+        "objects/Deal__c.object-meta.xml",
+        &OBJECT.replace("<fullName>All</fullName>", "<fullName>a@b</fullName>"),
+    );
+    for expr in [
+        r#"//listViews[@name="all"]"#,
+        r#"//listViews[fullName="a@b"]"#,
+    ] {
+        let out = run(&dir, &["query", expr, "objects"]);
+        assert_eq!(
+            stdout(&out),
+            "/CustomObject[0]/listViews[0]/fullName[0]/#text = a@b\n",
+            "{expr}"
+        );
+    }
+
+    let out = run(
+        &dir,
+        &[
+            "query",
+            r#"//listViews[@name="all"]"#,
+            "objects",
+            "--include-attributes",
+        ],
+    );
+    assert!(
+        stdout(&out).contains("/CustomObject[0]/listViews[0]/@name = all\n"),
+        "{}",
+        stdout(&out)
+    );
+}
@@ -0,0 +1,171 @@
+#![cfg(feature = "cli")]
+
+mod common;
+
+use common::{run, scratch, stderr, stdout, write};
+use std::path::{Path, PathBuf};
+
+const FIELD: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<CustomField xmlns="http://soap.sforce.com/2006/04/metadata" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
+    <fullName>Rev__c</fullName>
+    <description>Annual revenue, converted to the corporate currency.</description>
+    <defaultValue xsi:type="xsd:string">0</defaultValue>
+</CustomField>
+"#;
+
+const PLAIN: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<CustomField xmlns="http://soap.sforce.com/2006/04/metadata">
+    <fullName>Rev__c</fullName>
+    <label>Revenue</label>
+</CustomField>
+"#;
+
+/// Two copies of `source` and their rendering with `args`, as a wrapped TOON file.
+fn render(name: &str, source: &str, args: &[&str]) -> (PathBuf, String) {
+    let dir = scratch(name);
+    write(&dir, "fields/Rev__c.field-meta.xml", source);
+    write(&dir, "fields/Cost__c.field-meta.xml", source);
+    let mut all = vec!["--input", "fields", "--format", "toon", "--no-cache"];
+    all.extend_from_slice(args);
+    let out = run(&dir, &all);
+    assert!(out.status.success(), "{}", stderr(&out));
+    write(&dir, "repo.toon", &stdout(&out));
+    (dir, stdout(&out))
+}
+
+fn in_place(dir: &Path, extra: &[&str]) -> std::process::Output {
+    let mut args = vec!["to-xml", "repo.toon", "--in-place"];
+    args.extend_from_slice(extra);
+    run(dir, &args)
+}
+
+fn sources_equal(dir: &Path, source: &str) -> bool {
+    ["Rev__c", "Cost__c"].iter().all(|name| {
+        let path = dir.join(format!("fields/{name}.field-meta.xml"));
+        std::fs::read_to_string(path).unwrap() == source
+    })
+}
+
+fn assert_refused(dir: &Path, source: &str, reason: &str) {
+    let out = in_place(dir, &[]);
+    assert!(!out.status.success());
+    assert!(stderr(&out).contains(reason), "{}", stderr(&out));
+    assert!(sources_equal(dir, source));
+}
+
+#[test]
+fn in_place_refuses_truncated_values() {
+    let (dir, toon) = render(
+        "to-xml-truncated",
+        FIELD,
+        &[
+            "--include-attributes",
+            "--ns-mode",
+            "prefix",
+            "--max-text-len",
+            "10",
+        ],
+    );
+    assert!(toon.contains("chars]"));
+    assert_refused(&dir, FIELD, "was truncated");
+
+    let out = in_place(&dir, &["--force"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(!sources_equal(&dir, FIELD));
+}
+
+#[test]
+fn in_place_refuses_output_without_attributes() {
+    let (dir, _) = render("to-xml-no-attrs", FIELD, &["--max-text-len", "0"]);
+    assert_refused(&dir, FIELD, "no attribute entries");
+}
+
+#[test]
+fn in_place_refuses_strip_mode_namespaces() {
+    let (dir, _) = render(
+        "to-xml-strip-ns",
+        FIELD,
+        &["--include-attributes", "--max-text-len", "0"],
+    );
+    assert_refused(&dir, FIELD, "--ns-mode prefix or clark");
+}
+
+#[test]
+fn in_place_round_trips_xsi_type() {
+    for mode in ["prefix", "clark"] {
+        let (dir, _) = render(
+            &format!("to-xml-xsi-{mode}"),
+            FIELD,
+            &[
+                "--include-attributes",
+                "--ns-mode",
+                mode,
+                "--max-text-len",
+                "0",
+            ],
+        );
+        let out = in_place(&dir, &[]);
+        assert!(out.status.success(), "{mode}: {}", stderr(&out));
+        assert!(sources_equal(&dir, FIELD), "{mode}");
+    }
+}
+
+#[test]
+fn in_place_accepts_files_with_only_the_default_namespace() {
+    let (dir, _) = render("to-xml-plain", PLAIN, &["--max-text-len", "0"]);
+    let out = in_place(&dir, &[]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(sources_equal(&dir, PLAIN));
+}
+
+#[test]
+fn in_place_refuses_to_drop_empty_elements() {
+    let source = PLAIN.replace(
+        "    <label>",
+        "    <inlineHelpText></inlineHelpText>\n    <label>",
+    );
+    let (dir, _) = render("to-xml-empty", &source, &["--max-text-len", "0"]);
+    assert_refused(&dir, &source, "/CustomField/inlineHelpText is empty");
+
+    let out = in_place(&dir, &["--force"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(sources_equal(&dir, PLAIN));
+}
+
+#[test]
+fn toon_keeps_backslashes_and_reads_newline_escapes() {
+    let dir = scratch("to-xml-backslashes");
+    write(
+        &dir,
+        "repo.toon",
+        concat!(
+            "/CustomObject[0]/description[0]/#text = Export to C:\\Temp\\exports\n",
+            "/CustomObject[0]/validationRules[0]/errorConditionFormula[0]/#text = ^\\d{3}-\\d{4}$\n",
+            "/CustomObject[0]/validationRules[0]/errorMessage[0]/#text = Line one\\nLine two\n",
+        ),
+    );
+
+    let out = run(&dir, &["to-xml", "repo.toon"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    let xml = stdout(&out);
+    assert!(
+        xml.contains(r"<description>Export to C:\Temp\exports</description>"),
+        "{xml}"
+    );
+    assert!(
+        xml.contains(r"<errorConditionFormula>^\d{3}-\d{4}$</errorConditionFormula>"),
+        "{xml}"
+    );
+    assert!(
+        xml.contains("<errorMessage>Line one\nLine two</errorMessage>"),
+        "{xml}"
+    );
+}
+
+#[test]
+fn in_place_refuses_literal_newline_escapes_from_toon() {
+    let source = PLAIN.replace("Revenue", r"Split on \n");
+    let (dir, toon) = render("to-xml-literal-n", &source, &["--max-text-len", "0"]);
+    assert!(toon.contains(r"Split on \n"), "{toon}");
+    assert_refused(&dir, &source, "label[0]/#text contains a literal");
+}
@@ -0,0 +1,70 @@
+#[cfg(feature = "tokens")]
+use sfmeta_reader::Tokenizer;
+use sfmeta_reader::{FlattenOptions, flatten_str};
+
+fn text_values(xml: &str, opts: &FlattenOptions) -> Vec<String> {
+    flatten_str(xml, opts)
+        .unwrap()
+        .into_iter()
+        .map(|e| e.v)
+        .collect()
+}
+
+fn cut_to(len: usize) -> FlattenOptions {
+    FlattenOptions::builder().max_text_len(len).build().unwrap()
+}
+
+#[test]
+fn multibyte_text_is_cut_on_char_boundaries() {
+    for (text, cut) in [
+        ("ééééé", "éé…[+3 chars]"),
+        ("日本語のテキスト", "日本…[+6 chars]"),
+        ("a😀😀😀b", "a😀…[+3 chars]"),
+    ] {
+        let xml = format!("<r><d>{text}</d></r>");
+        assert_eq!(text_values(&xml, &cut_to(2)), [cut], "{text}");
+    }
+}
+
+#[test]
+fn short_values_and_unlimited_length_are_left_alone() {
+    let xml = "<r><d>ééé</d></r>";
+    assert_eq!(text_values(xml, &cut_to(3)), ["ééé"]);
+    assert_eq!(text_values(xml, &cut_to(0)), ["ééé"]);
+}
+
+#[test]
+fn never_truncate_protects_matching_elements() {
+    let xml = "<r><errorConditionFormula>ISBLANK(Name)</errorConditionFormula>\
+               <description>Long description</description></r>";
+    assert_eq!(
+        text_values(xml, &cut_to(4)),
+        ["ISBLANK(Name)", "Long…[+12 chars]"]
+    );
+
+    let opts = FlattenOptions::builder()
+        .max_text_len(4)
+        .never_truncate(["/r/description"])
+        .build()
+        .unwrap();
+    assert_eq!(
+        text_values(xml, &opts),
+        ["ISBL…[+9 chars]", "Long description"]
+    );
+}
+
+#[cfg(feature = "tokens")]
+#[test]
+fn token_truncation_marks_removed_tokens() {
+    let opts = FlattenOptions::builder()
+        .max_text_len(3)
+        .truncate_by_tokens(Tokenizer::Cl100k)
+        .build()
+        .unwrap();
+    let values = text_values(
+        "<r><d>Ünïcödé text that runs well past three tokens</d></r>",
+        &opts,
+    );
+    assert!(values[0].ends_with(" tokens]"), "{values:?}");
+    assert!(values[0].starts_with('Ü'), "{values:?}");
+}
@@ -0,0 +1,98 @@
+use sfmeta_reader::{FlattenOptions, YamlMode, flatten_str, render_yaml};
+
+fn yaml(xml: &str, mode: YamlMode) -> String {
+    let opts = FlattenOptions::builder()
+        .include_attributes(true)
+        .max_text_len(0)
+        .build()
+        .unwrap();
+    render_yaml(&flatten_str(xml, &opts).unwrap(), mode).unwrap()
+}
+
+const OBJECT: &str = r#"<CustomObject xmlns="http://soap.sforce.com/2006/04/metadata">
+    <description>Line one
+Line two</description>
+    <fields><fullName>Rev__c</fullName><precision>18</precision><required>true</required></fields>
+    <fields><fullName>Deal__c</fullName><label>Deal: total</label></fields>
+    <listViews name="all"><columns>NAME</columns><columns>OWNER</columns></listViews>
+</CustomObject>
+"#;
+
+#[test]
+fn tree_mode_nests_in_document_order() {
+    assert_eq!(
+        yaml(OBJECT, YamlMode::Tree),
+        "CustomObject:
+  description: |-
+    Line one
+    Line two
+  fields:
+  - fullName: Rev__c
+    precision: '18'
+    required: 'true'
+  - fullName: Deal__c
+    label: 'Deal: total'
+  listViews:
+    '@name': all
+    columns:
+    - NAME
+    - OWNER
+"
+    );
+}
+
+#[test]
+fn flat_mode_writes_one_pair_per_path() {
+    assert_eq!(
+        yaml(OBJECT, YamlMode::Flat),
+        "/CustomObject[0]/description[0]/#text: |-
+  Line one
+  Line two
+/CustomObject[0]/fields[0]/fullName[0]/#text: Rev__c
+/CustomObject[0]/fields[0]/precision[0]/#text: '18'
+/CustomObject[0]/fields[0]/required[0]/#text: 'true'
+/CustomObject[0]/fields[1]/fullName[0]/#text: Deal__c
+/CustomObject[0]/fields[1]/label[0]/#text: 'Deal: total'
+/CustomObject[0]/listViews[0]/@name: all
+/CustomObject[0]/listViews[0]/columns[0]/#text: NAME
+/CustomObject[0]/listViews[0]/columns[1]/#text: OWNER
+"
+    );
+}
+
+#[test]
+fn flat_mode_lists_every_value_of_a_repeated_path() {
+    assert_eq!(
+        yaml("<x>Hi <b>there</b> friend</x>", YamlMode::Flat),
+        "/x[0]/#text:
+- 'Hi '
+- ' friend'
+/x[0]/b[0]/#text: there
+"
+    );
+}
+
+#[test]
+fn strings_that_would_read_back_differently_are_quoted() {
+    for (text, written) in [
+        ("null", "'null'"),
+        ("No", "'No'"),
+        ("1e5", "'1e5'"),
+        ("2024-01-01", "'2024-01-01'"),
+        ("- dash", "'- dash'"),
+        ("#hash", "'#hash'"),
+        ("a #b", "'a #b'"),
+        ("it's", "it's"),
+        ("'quoted'", "'''quoted'''"),
+        (r"C:\Temp", r"C:\Temp"),
+        ("tab\there", r#""tab\there""#),
+        ("=", "'='"),
+    ] {
+        let xml = format!("<r><v>{text}</v></r>");
+        assert_eq!(
+            yaml(&xml, YamlMode::Tree),
+            format!("r:\n  v: {written}\n"),
+            "{text:?}"
+        );
+    }
+}